@@ -0,0 +1,39 @@
+; Recurses 16 levels deep, which fills the default call stack, drawing a
+; staircase of dots on the way down and a row of dots on the way back up,
+; then the depth reached. Interpreters with a smaller stack overflow
+; before the staircase is finished.
+
+        LD I, dot
+        LD V0, 0                ; depth
+        LD V1, 0                ; x of the next dot going down
+        LD V2, 0                ; x of the next dot coming back up
+        LD V3, 20               ; y of the row drawn coming back up
+        CALL recurse
+
+        ; Show the deepest level reached as two decimal digits.
+        LD I, digits
+        LD B, V0
+        LD V2, [I]
+        LD V4, 0
+        LD V5, 24
+        LD F, V1
+        DRW V4, V5, 5
+        LD V4, 5
+        LD F, V2
+        DRW V4, V5, 5
+
+end:    JP end
+
+recurse:
+        ADD V0, 1
+        DRW V1, V0, 1
+        ADD V1, 2
+        SE V0, 16
+        CALL recurse
+        LD I, dot
+        DRW V2, V3, 1
+        ADD V2, 2
+        RET
+
+dot:    DB 0x80
+digits: DB 0, 0, 0
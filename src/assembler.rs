@@ -0,0 +1,486 @@
+//! Assembler for CHIP-8 programs written with the mnemonics from Cowgod's
+//! technical reference, the same ones the disassembler prints.
+//!
+//! ```text
+//! ; Draws the digit in V0 in the top left corner
+//! digit   EQU 0x7
+//!
+//! start:  LD V0, digit
+//!         LD F, V0
+//!         DRW V1, V1, 5
+//! loop:   JP loop
+//!
+//! sprite: DB 0xF0, 0x90, %11110000
+//!         DW 0x1234
+//!         INCLUDE "more.asm"
+//! ```
+//!
+//! Labels end with a colon, constants are defined with EQU or =, and
+//! comments start with a semicolon. Numbers are decimal, hexadecimal with
+//! 0x, $ or #, or binary with 0b or %. Operands can add and subtract
+//! numbers, labels and constants. ORG moves the output forward to an address.
+//! The 4 byte XO-CHIP index load is written LD I, LONG followed by the address.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::instruction::{encode, Instruction};
+
+/// Address programs are loaded at, and where assembly starts.
+pub const ORIGIN: usize = 0x200;
+
+/// Highest address a program can occupy, XO-CHIP programs can use 64 KiB.
+const MEMORY_END: usize = 0x10000;
+
+/// How deep includes can nest before we assume they include each other.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// An error in the source, with the file and line it was found on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assembles source text into a ROM, includes are resolved relative to the
+/// current directory.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut lines = Vec::new();
+    expand("<source>", source, Path::new("."), 0, &mut lines)?;
+
+    Assembler::new(lines).run()
+}
+
+/// Assembles a source file into a ROM, includes are resolved relative to
+/// the directory of the file including them.
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, AsmError> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|err| AsmError {
+        file: name.clone(),
+        line: 0,
+        message: format!("could not read file: {}", err),
+    })?;
+
+    let mut lines = Vec::new();
+    expand(&name, &source, &parent(path), 0, &mut lines)?;
+
+    Assembler::new(lines).run()
+}
+
+fn parent(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// A line of source along with where it came from.
+struct Line {
+    file: String,
+    number: usize,
+    text: String,
+}
+
+impl Line {
+    fn error(&self, message: String) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.number, message }
+    }
+}
+
+/// Splits the source into lines, replacing INCLUDE directives with the
+/// lines of the included file.
+fn expand(file: &str, source: &str, dir: &Path, depth: usize, lines: &mut Vec<Line>) -> Result<(), AsmError> {
+    for (i, text) in source.lines().enumerate() {
+        let line = Line { file: file.to_string(), number: i + 1, text: strip_comment(text).to_string() };
+        let (label, rest) = split_label(&line.text);
+
+        match rest.split_once(char::is_whitespace) {
+            Some((directive, name)) if directive.eq_ignore_ascii_case("INCLUDE") => {
+                if depth == MAX_INCLUDE_DEPTH {
+                    return Err(line.error("includes are nested too deeply".to_string()));
+                }
+
+                let name = name.trim().trim_matches('"');
+                let path = dir.join(name);
+                let included = fs::read_to_string(&path)
+                    .map_err(|err| line.error(format!("could not include '{}': {}", name, err)))?;
+
+                // Keep any label on the include line pointing at the included code.
+                if let Some(label) = label {
+                    lines.push(Line { text: format!("{}:", label), ..line });
+                }
+
+                expand(&path.display().to_string(), &included, &parent(&path), depth + 1, lines)?;
+            },
+            _ => lines.push(line),
+        }
+    }
+
+    Ok(())
+}
+
+fn strip_comment(text: &str) -> &str {
+    text.split(';').next().unwrap_or("")
+}
+
+/// Splits a leading "label:" off the line, returning the label and the rest.
+fn split_label(text: &str) -> (Option<&str>, &str) {
+    let text = text.trim();
+
+    match text.split_once(':') {
+        Some((label, rest)) if is_identifier(label.trim()) => (Some(label.trim()), rest.trim()),
+        _ => (None, text),
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Operand of an instruction once parsed.
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    V(u8),
+    Range(u8, u8),
+    Long(String),
+    I,
+    IndirectI,
+    Hf,
+    R,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Value(String),
+}
+
+fn parse_operand(text: &str) -> Operand {
+    let upper = text.to_ascii_uppercase();
+
+    match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "HF" => Operand::Hf,
+        "R" => Operand::R,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        _ if upper.starts_with("LONG ") => Operand::Long(text[5..].trim().to_string()),
+        _ => match upper.split_once('-').map(|(x, y)| (register(x), register(y))) {
+            Some((Some(x), Some(y))) => Operand::Range(x, y),
+            _ => match register(&upper) {
+                Some(x) => Operand::V(x),
+                None => Operand::Value(text.to_string()),
+            },
+        },
+    }
+}
+
+/// Parses a register name like VA into its index.
+fn register(text: &str) -> Option<u8> {
+    let digit = text.trim().strip_prefix('V')?;
+
+    match digit.len() {
+        1 => u8::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+}
+
+/// A statement from the first pass, placed at its address.
+enum Statement {
+    Instruction { mnemonic: String, operands: Vec<Operand> },
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+}
+
+struct Assembler {
+    lines: Vec<Line>,
+    symbols: HashMap<String, i64>,
+}
+
+impl Assembler {
+
+    fn new(lines: Vec<Line>) -> Self {
+        Assembler { lines, symbols: HashMap::new() }
+    }
+
+    /// First pass places every statement and records the labels and
+    /// constants, the second pass encodes them now every symbol is known.
+    fn run(mut self) -> Result<Vec<u8>, AsmError> {
+        let mut placed: Vec<(usize, usize, Statement)> = Vec::new();
+        let mut address = ORIGIN;
+
+        let lines = std::mem::take(&mut self.lines);
+
+        for (index, line) in lines.iter().enumerate() {
+            let (label, rest) = split_label(&line.text);
+
+            if let Some(label) = label {
+                self.define(line, label, address as i64)?;
+            }
+
+            if rest.is_empty() {
+                continue;
+            }
+
+            let (word, args) = match rest.split_once(char::is_whitespace) {
+                Some((word, args)) => (word, args.trim()),
+                None => (rest, ""),
+            };
+
+            // Constants, NAME EQU value or NAME = value.
+            if let Some((name, value)) = args.split_once(char::is_whitespace)
+                .filter(|(directive, _)| directive.eq_ignore_ascii_case("EQU"))
+                .map(|(_, value)| (word, value))
+                .or_else(|| rest.split_once('=').map(|(name, value)| (name.trim(), value)))
+            {
+                if !is_identifier(name) {
+                    return Err(line.error(format!("invalid constant name '{}'", name)));
+                }
+
+                let value = self.evaluate(line, value.trim())?;
+                self.define(line, name, value)?;
+                continue;
+            }
+
+            let statement = match word.to_ascii_uppercase().as_str() {
+                "ORG" => {
+                    let target = self.evaluate(line, args)?;
+
+                    if target < address as i64 || target as usize > MEMORY_END {
+                        return Err(line.error(format!("ORG {:#05X} is behind {:#05X} or outside memory", target, address)));
+                    }
+
+                    address = target as usize;
+                    continue;
+                },
+                "DB" => Statement::Bytes(split_operands(args)),
+                "DW" => Statement::Words(split_operands(args)),
+                _ => Statement::Instruction {
+                    mnemonic: word.to_ascii_uppercase(),
+                    operands: split_operands(args).iter().map(|operand| parse_operand(operand)).collect(),
+                },
+            };
+
+            let size = match &statement {
+                Statement::Instruction { operands, .. } if operands.iter().any(|o| matches!(o, Operand::Long(_))) => 4,
+                Statement::Instruction { .. } => 2,
+                Statement::Bytes(values) => values.len(),
+                Statement::Words(values) => values.len() * 2,
+            };
+
+            placed.push((index, address, statement));
+            address += size;
+
+            if address > MEMORY_END {
+                return Err(line.error("program does not fit in memory".to_string()));
+            }
+        }
+
+        let mut rom = vec![0; address - ORIGIN];
+
+        for (index, address, statement) in placed {
+            let line = &lines[index];
+            let offset = address - ORIGIN;
+
+            match statement {
+                Statement::Instruction { mnemonic, operands } => {
+                    let opcode = encode(&self.instruction(line, &mnemonic, &operands)?);
+                    rom[offset..offset + 2].copy_from_slice(&opcode.to_be_bytes());
+
+                    // The long index load keeps its address in the following word.
+                    if let [_, Operand::Long(value)] = operands.as_slice() {
+                        let address = self.ranged(line, value, 0, 0xFFFF)? as u16;
+                        rom[offset + 2..offset + 4].copy_from_slice(&address.to_be_bytes());
+                    }
+                },
+                Statement::Bytes(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        rom[offset + i] = self.byte(line, value)?;
+                    }
+                },
+                Statement::Words(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        let word = self.ranged(line, value, -0x8000, 0xFFFF)? as u16;
+                        rom[offset + i * 2..offset + i * 2 + 2].copy_from_slice(&word.to_be_bytes());
+                    }
+                },
+            }
+        }
+
+        Ok(rom)
+    }
+
+    fn define(&mut self, line: &Line, name: &str, value: i64) -> Result<(), AsmError> {
+        let key = name.to_ascii_lowercase();
+
+        if self.symbols.contains_key(&key) {
+            return Err(line.error(format!("'{}' is already defined", name)));
+        }
+
+        self.symbols.insert(key, value);
+        Ok(())
+    }
+
+    /// Builds the instruction for the mnemonic and its operands.
+    fn instruction(&self, line: &Line, mnemonic: &str, operands: &[Operand]) -> Result<Instruction, AsmError> {
+        use Operand::*;
+
+        let instruction = match (mnemonic, operands) {
+            ("CLS", [])                     => Instruction::Cls,
+            ("RET", [])                     => Instruction::Ret,
+            ("SCD", [Value(n)])             => Instruction::Scd(self.ranged(line, n, 0, 0xF)? as u8),
+            ("SCU", [Value(n)])             => Instruction::Scu(self.ranged(line, n, 0, 0xF)? as u8),
+            ("SCR", [])                     => Instruction::Scr,
+            ("SCL", [])                     => Instruction::Scl,
+            ("EXIT", [])                    => Instruction::Exit,
+            ("LOW", [])                     => Instruction::Low,
+            ("HIGH", [])                    => Instruction::High,
+            ("SYS", [Value(a)])             => Instruction::Sys(self.address(line, a)?),
+            ("JP", [Value(a)])              => Instruction::Jp(self.address(line, a)?),
+            ("JP", [V(0), Value(a)])        => Instruction::JpV0(self.address(line, a)?),
+            ("CALL", [Value(a)])            => Instruction::Call(self.address(line, a)?),
+            ("SE", [V(x), V(y)])            => Instruction::SeReg(*x, *y),
+            ("SE", [V(x), Value(nn)])       => Instruction::SeByte(*x, self.byte(line, nn)?),
+            ("SNE", [V(x), V(y)])           => Instruction::SneReg(*x, *y),
+            ("SNE", [V(x), Value(nn)])      => Instruction::SneByte(*x, self.byte(line, nn)?),
+            ("LD", [V(x), V(y)])            => Instruction::LdReg(*x, *y),
+            ("LD", [V(x), Value(nn)])       => Instruction::LdByte(*x, self.byte(line, nn)?),
+            ("LD", [I, Value(a)])           => Instruction::LdI(self.address(line, a)?),
+            ("LD", [I, Long(_)])            => Instruction::LdILong,
+            ("LD", [IndirectI, Range(x, y)]) => Instruction::LdIRange(*x, *y),
+            ("LD", [Range(x, y), IndirectI]) => Instruction::LdRangeI(*x, *y),
+            ("PLANE", [Value(n)])           => Instruction::Plane(self.ranged(line, n, 0, 0xF)? as u8),
+            ("AUDIO", [])                   => Instruction::Audio,
+            ("PITCH", [V(x)])               => Instruction::Pitch(*x),
+            ("LD", [V(x), Dt])              => Instruction::LdVxDt(*x),
+            ("LD", [V(x), K])               => Instruction::LdVxK(*x),
+            ("LD", [Dt, V(x)])              => Instruction::LdDtVx(*x),
+            ("LD", [St, V(x)])              => Instruction::LdStVx(*x),
+            ("LD", [F, V(x)])               => Instruction::LdF(*x),
+            ("LD", [Hf, V(x)])              => Instruction::LdHf(*x),
+            ("LD", [B, V(x)])               => Instruction::LdB(*x),
+            ("LD", [IndirectI, V(x)])       => Instruction::LdIVx(*x),
+            ("LD", [V(x), IndirectI])       => Instruction::LdVxI(*x),
+            ("LD", [R, V(x)])               => Instruction::LdRVx(*x),
+            ("LD", [V(x), R])               => Instruction::LdVxR(*x),
+            ("ADD", [V(x), V(y)])           => Instruction::AddReg(*x, *y),
+            ("ADD", [V(x), Value(nn)])      => Instruction::AddByte(*x, self.byte(line, nn)?),
+            ("ADD", [I, V(x)])              => Instruction::AddI(*x),
+            ("OR", [V(x), V(y)])            => Instruction::Or(*x, *y),
+            ("AND", [V(x), V(y)])           => Instruction::And(*x, *y),
+            ("XOR", [V(x), V(y)])           => Instruction::Xor(*x, *y),
+            ("SUB", [V(x), V(y)])           => Instruction::Sub(*x, *y),
+            ("SUBN", [V(x), V(y)])          => Instruction::Subn(*x, *y),
+            ("SHR", [V(x)])                 => Instruction::Shr(*x, *x),
+            ("SHR", [V(x), V(y)])           => Instruction::Shr(*x, *y),
+            ("SHL", [V(x)])                 => Instruction::Shl(*x, *x),
+            ("SHL", [V(x), V(y)])           => Instruction::Shl(*x, *y),
+            ("RND", [V(x), Value(nn)])      => Instruction::Rnd(*x, self.byte(line, nn)?),
+            ("DRW", [V(x), V(y), Value(n)]) => Instruction::Drw(*x, *y, self.ranged(line, n, 0, 0xF)? as u8),
+            ("SKP", [V(x)])                 => Instruction::Skp(*x),
+            ("SKNP", [V(x)])                => Instruction::Sknp(*x),
+            _ => return Err(line.error(format!("invalid instruction '{}'", line.text.trim()))),
+        };
+
+        Ok(instruction)
+    }
+
+    fn address(&self, line: &Line, text: &str) -> Result<u16, AsmError> {
+        Ok(self.ranged(line, text, 0, 0xFFF)? as u16)
+    }
+
+    /// Bytes can also be written as negative numbers, which are stored in two's complement.
+    fn byte(&self, line: &Line, text: &str) -> Result<u8, AsmError> {
+        Ok(self.ranged(line, text, -0x80, 0xFF)? as u8)
+    }
+
+    fn ranged(&self, line: &Line, text: &str, min: i64, max: i64) -> Result<i64, AsmError> {
+        let value = self.evaluate(line, text)?;
+
+        if value < min || value > max {
+            return Err(line.error(format!("value {} of '{}' is out of range", value, text)));
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluates a sum of numbers and symbols, like "sprite + 5 - $1".
+    fn evaluate(&self, line: &Line, text: &str) -> Result<i64, AsmError> {
+        let mut total: i64 = 0;
+        let mut sign = 1;
+        let mut term = String::new();
+        let mut first = true;
+
+        for c in text.chars().chain(std::iter::once('+')) {
+            if c != '+' && c != '-' {
+                term.push(c);
+                continue;
+            }
+
+            if term.trim().is_empty() {
+                // Only a leading minus sign can come without a term before it.
+                if c == '+' || !first || sign == -1 {
+                    return Err(line.error(format!("invalid expression '{}'", text)));
+                }
+
+                sign = -1;
+                continue;
+            }
+
+            total = self.term(line, term.trim())?
+                .checked_mul(sign)
+                .and_then(|value| total.checked_add(value))
+                .ok_or_else(|| line.error(format!("expression '{}' overflows", text.trim())))?;
+            term.clear();
+            sign = if c == '-' { -1 } else { 1 };
+            first = false;
+        }
+
+        Ok(total)
+    }
+
+    fn term(&self, line: &Line, text: &str) -> Result<i64, AsmError> {
+        let lower = text.to_ascii_lowercase();
+
+        let parsed = if let Some(hex) = lower.strip_prefix("0x").or(lower.strip_prefix('$')).or(lower.strip_prefix('#')) {
+            i64::from_str_radix(hex, 16).ok()
+        } else if let Some(binary) = lower.strip_prefix("0b").or(lower.strip_prefix('%')) {
+            i64::from_str_radix(binary, 2).ok()
+        } else if lower.starts_with(|c: char| c.is_ascii_digit()) {
+            lower.parse::<i64>().ok()
+        } else {
+            return self.symbols.get(&lower).copied()
+                .ok_or_else(|| line.error(format!("undefined symbol '{}'", text)));
+        };
+
+        parsed.ok_or_else(|| line.error(format!("invalid number '{}'", text)))
+    }
+}
+
+fn split_operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+
+    text.split(',').map(|operand| operand.trim().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests;
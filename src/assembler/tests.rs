@@ -0,0 +1,124 @@
+use std::fs;
+
+use crate::assembler::{assemble, assemble_file, AsmError};
+use crate::instruction::decode;
+
+#[test]
+fn test_assemble() {
+    let source = "
+        ; Draws the digit 7 forever
+        digit EQU 7
+
+        start:  CLS
+                LD V0, digit
+                LD F, V0
+                DRW V1, V1, 5
+        loop:   JP loop
+    ";
+
+    let rom = assemble(source).unwrap();
+    assert_eq!(rom, vec![0x00, 0xE0, 0x60, 0x07, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x08]);
+}
+
+#[test]
+fn test_forward_labels_and_data() {
+    let source = "
+        CALL draw
+        JP   self
+        self = 0x202
+
+        draw:   LD I, sprite + 1
+                RET
+        sprite: db 0xF0, #90, %10010000, 0b1111, -1
+                DW 0x1234, sprite
+    ";
+
+    let rom = assemble(source).unwrap();
+    assert_eq!(
+        rom,
+        vec![0x22, 0x04, 0x12, 0x02, 0xA2, 0x09, 0x00, 0xEE, 0xF0, 0x90, 0x90, 0x0F, 0xFF, 0x12, 0x34, 0x02, 0x08]
+    );
+}
+
+#[test]
+fn test_mnemonics_are_case_insensitive() {
+    assert_eq!(assemble("ld va, 0x02\nshr v3\nShl V3, v4").unwrap(), vec![0x6A, 0x02, 0x83, 0x36, 0x83, 0x4E]);
+}
+
+#[test]
+fn test_org() {
+    assert_eq!(assemble("JP end\nORG 0x206\nend: RET").unwrap(), vec![0x12, 0x06, 0, 0, 0, 0, 0x00, 0xEE]);
+    assert!(assemble("CLS\nORG 0x200").is_err());
+}
+
+#[test]
+fn test_errors() {
+    let error = |source: &str| assemble(source).unwrap_err();
+
+    assert_eq!(
+        error("CLS\nJP nowhere"),
+        AsmError { file: "<source>".to_string(), line: 2, message: "undefined symbol 'nowhere'".to_string() }
+    );
+    assert_eq!(error("LD V0, 0x100").message, "value 256 of '0x100' is out of range");
+    assert_eq!(error("DRW V0, V1, 16").line, 1);
+    assert_eq!(error("\n\nLD I, V0").message, "invalid instruction 'LD I, V0'");
+    assert_eq!(error("a: CLS\na: CLS").message, "'a' is already defined");
+    assert_eq!(error("DB 0xZZ").message, "invalid number '0xZZ'");
+    assert_eq!(error("INCLUDE \"missing.asm\"").line, 1);
+    assert_eq!(error("x EQU 1 +").message, "invalid expression '1 +'");
+    assert_eq!(
+        error("DW 0x7FFFFFFFFFFFFFFF + 1").message,
+        "expression '0x7FFFFFFFFFFFFFFF + 1' overflows",
+    );
+    assert_eq!(
+        error("x EQU 0x7FFFFFFFFFFFFFFF\nDW -x - x").message,
+        "expression '-x - x' overflows",
+    );
+}
+
+#[test]
+fn test_include() {
+    let dir = std::env::temp_dir().join(format!("chip8-asm-test-{}", std::process::id()));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+
+    fs::write(dir.join("main.asm"), "CALL draw\nlib: INCLUDE \"lib/draw.asm\"\n").unwrap();
+    fs::write(dir.join("lib/draw.asm"), "INCLUDE \"data.asm\"\ndraw: RET\n").unwrap();
+    fs::write(dir.join("lib/data.asm"), "DB 1, 2\n").unwrap();
+
+    let rom = assemble_file(&dir.join("main.asm"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(rom.unwrap(), vec![0x22, 0x04, 0x01, 0x02, 0x00, 0xEE]);
+}
+
+#[test]
+fn test_assembles_every_mnemonic() {
+    // Everything the disassembler prints must assemble back to the same opcode.
+    for opcode in 0..=0xFFFF_u16 {
+        if let Some(instruction) = decode(opcode).ok().filter(|instruction| instruction.size() == 2) {
+            let rom = assemble(&instruction.to_string()).unwrap();
+            assert_eq!(u16::from_be_bytes([rom[0], rom[1]]), opcode, "{}", instruction);
+        }
+    }
+}
+
+#[test]
+fn test_xo_chip() {
+    let source = "
+        LD I, LONG data
+        LD [I], V2-V5
+        LD V5-V2, [I]
+        PLANE 3
+        AUDIO
+        PITCH VA
+        SCU 2
+        ORG 0x1000
+    data:
+        DB 1
+    ";
+
+    let rom = assemble(source).unwrap();
+
+    assert_eq!(rom[..16], [0xF0, 0x00, 0x10, 0x00, 0x52, 0x52, 0x55, 0x23, 0xF3, 0x01, 0xF0, 0x02, 0xFA, 0x3A, 0x00, 0xD2]);
+    assert_eq!(rom.len(), 0x1000 - 0x200 + 1);
+}
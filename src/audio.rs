@@ -0,0 +1,158 @@
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+
+use chip_8_rust::processor::processor::{pattern_rate, PATTERN_SIZE};
+
+/// Something that can play the CHIP-8 beep while the sound timer is active.
+pub trait Beeper {
+
+    /// Starts the tone, calling this while already playing does nothing.
+    fn play(&mut self);
+
+    /// Stops the tone, calling this while already paused does nothing.
+    fn pause(&mut self);
+
+    /// Plays the XO-CHIP audio pattern at the pitch instead of the plain
+    /// tone, or goes back to the tone when there is no pattern.
+    fn set_pattern(&mut self, _pattern: Option<&[u8; PATTERN_SIZE]>, _pitch: u8) {}
+
+    /// Plays or pauses the tone depending on the value of the sound timer.
+    fn update(&mut self, sound_timer: u8) {
+        if sound_timer > 0 {
+            self.play();
+        } else {
+            self.pause();
+        }
+    }
+}
+
+/// Beeper that never makes a sound, used when running headless or muted.
+pub struct NullBeeper;
+
+impl Beeper for NullBeeper {
+    fn play(&mut self) {}
+
+    fn pause(&mut self) {}
+}
+
+/// Generates a square wave at a fixed frequency for the SDL audio callback,
+/// or loops over the bits of an XO-CHIP audio pattern when one is set.
+pub struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+    pattern: Option<[u8; PATTERN_SIZE]>,
+    pattern_inc: f32,
+    pattern_pos: f32,
+    freq: f32,
+}
+
+impl SquareWave {
+
+    /// Creates a wave at the pitch in hertz for a device playing freq samples
+    /// a second, the volume is a percentage from 0 to 100.
+    pub fn new(freq: i32, pitch: u32, volume: u8) -> Self {
+        SquareWave {
+            phase_inc: pitch as f32 / freq as f32,
+            phase: 0.0,
+            volume: volume.min(100) as f32 / 100.0,
+            pattern: None,
+            pattern_inc: 0.0,
+            pattern_pos: 0.0,
+            freq: freq as f32,
+        }
+    }
+
+    /// Loops over the XO-CHIP audio pattern at the rate for the pitch
+    /// register, or goes back to the plain tone without a pattern.
+    pub fn set_pattern(&mut self, pattern: Option<[u8; PATTERN_SIZE]>, pitch: u8) {
+        self.pattern = pattern;
+        self.pattern_inc = pattern_rate(pitch) / self.freq;
+        self.pattern_pos = 0.0;
+    }
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        let bits = (PATTERN_SIZE * 8) as f32;
+
+        for sample in out.iter_mut() {
+            let high = match &self.pattern {
+                Some(pattern) => {
+                    let bit = self.pattern_pos as usize;
+                    self.pattern_pos = (self.pattern_pos + self.pattern_inc) % bits;
+
+                    pattern[bit / 8] & (0x80 >> (bit % 8)) != 0
+                },
+                None => {
+                    self.phase = (self.phase + self.phase_inc) % 1.0;
+                    self.phase <= 0.5
+                },
+            };
+
+            *sample = if high { self.volume } else { -self.volume };
+        }
+    }
+}
+
+/// Beeper that plays a square wave through SDL's audio subsystem.
+pub struct SdlBeeper {
+    device: AudioDevice<SquareWave>,
+    playing: bool,
+    pattern: Option<[u8; PATTERN_SIZE]>,
+    pitch: u8,
+}
+
+impl SdlBeeper {
+
+    /// Opens the default audio device, the pitch is in hertz and the volume
+    /// is a percentage from 0 to 100.
+    pub fn new(sdl_context: &Sdl, pitch: u32, volume: u8) -> Result<Self, String> {
+        let audio_subsystem = sdl_context.audio()?;
+
+        let desired_spec = AudioSpecDesired {
+            freq: Some(44_100),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
+            SquareWave::new(spec.freq, pitch, volume)
+        })?;
+
+        Ok(SdlBeeper { device, playing: false, pattern: None, pitch: 0 })
+    }
+}
+
+impl Beeper for SdlBeeper {
+    fn play(&mut self) {
+        if !self.playing {
+            self.device.resume();
+            self.playing = true;
+        }
+    }
+
+    fn pause(&mut self) {
+        if self.playing {
+            self.device.pause();
+            self.playing = false;
+        }
+    }
+
+    fn set_pattern(&mut self, pattern: Option<&[u8; PATTERN_SIZE]>, pitch: u8) {
+        if self.pattern.as_ref() == pattern && self.pitch == pitch {
+            return;
+        }
+
+        self.pattern = pattern.copied();
+        self.pitch = pitch;
+
+        // Locking pauses the callback while the pattern is swapped.
+        self.device.lock().set_pattern(self.pattern, pitch);
+    }
+}
+
+#[cfg(test)]
+mod tests;
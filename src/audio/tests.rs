@@ -0,0 +1,91 @@
+use chip_8_rust::processor::processor::DEFAULT_PATTERN_PITCH;
+
+use super::*;
+
+/// Beeper that remembers whether it was told to play.
+struct RecordingBeeper {
+    playing: bool,
+    calls: u32,
+}
+
+impl Beeper for RecordingBeeper {
+    fn play(&mut self) {
+        self.playing = true;
+        self.calls += 1;
+    }
+
+    fn pause(&mut self) {
+        self.playing = false;
+        self.calls += 1;
+    }
+}
+
+#[test]
+fn test_square_wave() {
+    // Two cycles a second at 8 samples a second, so the output is high for
+    // the first half of each 4 sample period.
+    let mut wave = SquareWave::new(8, 2, 50);
+    let mut out = [0.0; 8];
+
+    wave.callback(&mut out);
+
+    assert_eq!(out, [0.5, 0.5, -0.5, 0.5, 0.5, 0.5, -0.5, 0.5]);
+}
+
+#[test]
+fn test_volume_is_clamped() {
+    let mut wave = SquareWave::new(8, 2, 200);
+    let mut out = [0.0; 4];
+
+    wave.callback(&mut out);
+
+    assert_eq!(out, [1.0, 1.0, -1.0, 1.0]);
+}
+
+#[test]
+fn test_pattern_playback() {
+    let mut pattern = [0; PATTERN_SIZE];
+    pattern[0] = 0b1010_0000;
+    pattern[PATTERN_SIZE - 1] = 0b0000_0001;
+
+    // The default pitch plays 4000 pattern bits a second, so at 8000 samples
+    // a second every bit lasts two samples.
+    let mut wave = SquareWave::new(8000, 440, 100);
+    wave.set_pattern(Some(pattern), DEFAULT_PATTERN_PITCH);
+    assert_eq!(pattern_rate(DEFAULT_PATTERN_PITCH), 4000.0);
+
+    let mut out = [0.0; 8];
+    wave.callback(&mut out);
+    assert_eq!(out, [1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0]);
+
+    // The pattern loops around after its last bit.
+    let mut rest = [0.0; PATTERN_SIZE * 16 - 8];
+    wave.callback(&mut rest);
+    assert_eq!(&rest[rest.len() - 2..], &[1.0, 1.0]);
+
+    wave.callback(&mut out[..2]);
+    assert_eq!(&out[..2], &[1.0, 1.0]);
+
+    // Without a pattern the plain tone plays again.
+    wave.set_pattern(None, DEFAULT_PATTERN_PITCH);
+    wave.callback(&mut out);
+    assert!(out.iter().all(|&sample| sample.abs() == 1.0));
+}
+
+#[test]
+fn test_update_follows_sound_timer() {
+    let mut beeper = RecordingBeeper { playing: false, calls: 0 };
+
+    beeper.update(3);
+    assert!(beeper.playing);
+
+    beeper.update(0);
+    assert!(!beeper.playing);
+    assert_eq!(beeper.calls, 2);
+
+    // The null beeper takes the same calls without doing anything.
+    let mut null = NullBeeper;
+    null.update(3);
+    null.set_pattern(Some(&[0xFF; PATTERN_SIZE]), DEFAULT_PATTERN_PITCH);
+    null.update(0);
+}
@@ -0,0 +1,227 @@
+use chip_8_rust::collections::DEFAULT_STACK_DEPTH;
+use chip_8_rust::processor::policy::{OpcodePolicy, POLICIES};
+use chip_8_rust::scheduler::DEFAULT_SPEED;
+use chip_8_rust::SCALE;
+
+/// The conventional layout, listing the host key for each keypad key from 0 to F.
+///
+/// 1 2 3 C        1 2 3 4
+/// 4 5 6 D   ->   Q W E R
+/// 7 8 9 E        A S D F
+/// A 0 B F        Z X C V
+pub const DEFAULT_LAYOUT: &str = "X123QWEASDZC4RFV";
+
+/// Default pitch of the beep in hertz.
+pub const DEFAULT_PITCH: u32 = 440;
+
+/// Default volume of the beep as a percentage.
+pub const DEFAULT_VOLUME: u8 = 25;
+
+pub const USAGE: &str = "\
+Usage: chip-8-rust [OPTIONS] <ROM>
+       chip-8-rust disasm <ROM>
+       chip-8-rust asm <SOURCE> [-o <FILE>]
+
+Commands:
+    disasm               Print an annotated disassembly of the ROM
+    asm                  Assemble a source file into a ROM, written to FILE or
+                         next to the source with a .ch8 extension
+
+Options:
+    -s, --scale <N>      Size of each CHIP-8 pixel in window pixels (default 16)
+    -c, --speed <HZ>     Instructions executed per second (default 700)
+    -q, --quirks <NAME>  Interpreter behaviors to emulate, one of default, vip,
+                         chip48, schip or xochip (default default)
+        --stack-depth <N>
+                         Return addresses the call stack holds (default 16)
+        --on-unknown <POLICY>
+                         What to do with opcodes that are not instructions, one
+                         of trap, log or ignore (default trap)
+        --on-sys <POLICY>
+                         What to do with 0NNN machine code calls, one of trap,
+                         log or ignore (default ignore)
+    -k, --keys <LAYOUT>  16 host keys bound to keypad keys 0 through F
+                         (default X123QWEASDZC4RFV)
+    -p, --pitch <HZ>     Pitch of the beep (default 440)
+    -v, --volume <PCT>   Volume of the beep from 1 to 100 (default 25)
+    -m, --mute           Disable sound
+    -d, --debug          Start paused in the step debugger, commands are read
+                         from the terminal
+        --headless       Run without a window or sound and print the final state
+        --cycles <N>     Stop a headless run after N instructions
+        --frames <N>     Stop a headless run after N frames (default 600)
+        --dump <FILE>    Write the final headless framebuffer to a .png or .pbm
+                         file at the window scale instead of printing it
+        --trace <FILE>   Write a line for every instruction to the file
+        --trace-last <N> Only keep the last N traced instructions, written
+                         to the trace file if the emulator crashes
+        --trace-pc <START-END>
+                         Only trace instructions in the hexadecimal address range
+        --trace-op <LIST>
+                         Only trace opcodes starting with the hex digits in the
+                         comma separated list, like 1,2,D
+    -o, --output <FILE>  Where asm writes the assembled ROM
+    -h, --help           Print this message
+
+While running:
+    F5 / F9              Save / load the machine state in the current slot
+    F6 / F7              Select the previous / next save slot (0 to 9)
+    F12                  Save a screenshot to the current directory
+    F1                   Pause in the debugger when started with --debug";
+
+/// What the program was asked to do with the ROM.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Run,
+    Disasm,
+    Asm,
+}
+
+/// Options the emulator was started with.
+pub struct Options {
+    pub command: Command,
+    pub rom: String,
+    pub scale: usize,
+    pub speed: u32,
+    pub quirks: String,
+    pub stack_depth: usize,
+    pub on_unknown: OpcodePolicy,
+    pub on_sys: OpcodePolicy,
+    pub keys: String,
+    pub pitch: u32,
+    pub volume: u8,
+    pub mute: bool,
+    pub debug: bool,
+    pub headless: bool,
+    pub cycles: Option<u64>,
+    pub frames: Option<u64>,
+    pub dump: Option<String>,
+    pub trace: Option<String>,
+    pub trace_last: Option<usize>,
+    pub trace_pc: Option<String>,
+    pub trace_ops: Option<String>,
+    pub output: Option<String>,
+    pub help: bool,
+}
+
+impl Options {
+
+    /// Parses the command line arguments, not including the program name.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+        let mut rom: Option<String> = None;
+        let mut options = Options {
+            command: Command::Run,
+            rom: String::new(),
+            scale: SCALE,
+            speed: DEFAULT_SPEED,
+            quirks: "default".to_string(),
+            stack_depth: DEFAULT_STACK_DEPTH,
+            on_unknown: OpcodePolicy::Trap,
+            on_sys: OpcodePolicy::Ignore,
+            keys: DEFAULT_LAYOUT.to_string(),
+            pitch: DEFAULT_PITCH,
+            volume: DEFAULT_VOLUME,
+            mute: false,
+            debug: false,
+            headless: false,
+            cycles: None,
+            frames: None,
+            dump: None,
+            trace: None,
+            trace_last: None,
+            trace_pc: None,
+            trace_ops: None,
+            output: None,
+            help: false,
+        };
+
+        let mut first = true;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "disasm" if first => options.command = Command::Disasm,
+                "asm" if first   => options.command = Command::Asm,
+                "-h" | "--help"  => options.help = true,
+                "-s" | "--scale" => options.scale = parse_number(&arg, args.next())?,
+                "-c" | "--speed" => options.speed = parse_number(&arg, args.next())?,
+                "-q" | "--quirks" => options.quirks = value(&arg, args.next())?,
+                "--stack-depth"  => options.stack_depth = parse_number(&arg, args.next())?,
+                "--on-unknown"   => options.on_unknown = parse_policy(&arg, args.next())?,
+                "--on-sys"       => options.on_sys = parse_policy(&arg, args.next())?,
+                "-k" | "--keys"  => options.keys = value(&arg, args.next())?,
+                "-p" | "--pitch" => options.pitch = parse_number(&arg, args.next())?,
+                "-v" | "--volume" => options.volume = parse_number(&arg, args.next())?,
+                "-m" | "--mute"  => options.mute = true,
+                "-d" | "--debug" => options.debug = true,
+                "--headless"     => options.headless = true,
+                "--cycles"       => options.cycles = Some(parse_number(&arg, args.next())?),
+                "--frames"       => options.frames = Some(parse_number(&arg, args.next())?),
+                "--dump"         => options.dump = Some(value(&arg, args.next())?),
+                "--trace"        => options.trace = Some(value(&arg, args.next())?),
+                "--trace-last"   => options.trace_last = Some(parse_number(&arg, args.next())?),
+                "--trace-pc"     => options.trace_pc = Some(value(&arg, args.next())?),
+                "--trace-op"     => options.trace_ops = Some(value(&arg, args.next())?),
+                "-o" | "--output" => options.output = Some(value(&arg, args.next())?),
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument '{}'", arg)),
+            }
+
+            first = false;
+        }
+
+        if options.help {
+            return Ok(options);
+        }
+
+        if options.cycles.is_some() && options.frames.is_some() {
+            return Err("options '--cycles' and '--frames' cannot be used together".to_string());
+        }
+
+        let filtered = options.trace_last.is_some() || options.trace_pc.is_some() || options.trace_ops.is_some();
+        if filtered && options.trace.is_none() {
+            return Err("options '--trace-last', '--trace-pc' and '--trace-op' need '--trace'".to_string());
+        }
+
+        if options.output.is_some() && options.command != Command::Asm {
+            return Err("option '--output' can only be used with asm".to_string());
+        }
+
+        if options.volume > 100 {
+            return Err(format!("volume must be between 1 and 100, got {}", options.volume));
+        }
+
+        options.rom = rom.ok_or("no ROM file given")?;
+        Ok(options)
+    }
+}
+
+/// Returns the value following an option or an error naming the option.
+fn value(option: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("option '{}' needs a value", option))
+}
+
+/// Parses the value following an option as an opcode policy.
+fn parse_policy(option: &str, value: Option<String>) -> Result<OpcodePolicy, String> {
+    let name = self::value(option, value)?;
+
+    OpcodePolicy::parse(&name).ok_or_else(|| {
+        format!("option '{}' expects one of {}, got '{}'", option, POLICIES.join(", "), name)
+    })
+}
+
+/// Parses the value following an option as a positive number.
+fn parse_number<T>(option: &str, value: Option<String>) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + Default,
+{
+    let text = self::value(option, value)?;
+
+    match text.parse::<T>() {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(format!("option '{}' expects a positive number, got '{}'", option, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests;
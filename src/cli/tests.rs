@@ -0,0 +1,147 @@
+use super::*;
+
+fn parse(args: &[&str]) -> Result<Options, String> {
+    Options::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+#[test]
+fn test_defaults() {
+    let options = parse(&["pong.ch8"]).unwrap();
+
+    assert_eq!(options.command, Command::Run);
+    assert_eq!(options.rom, "pong.ch8");
+    assert_eq!(options.scale, SCALE);
+    assert_eq!(options.speed, DEFAULT_SPEED);
+    assert_eq!(options.quirks, "default");
+    assert_eq!(options.stack_depth, DEFAULT_STACK_DEPTH);
+    assert_eq!(options.on_unknown, OpcodePolicy::Trap);
+    assert_eq!(options.on_sys, OpcodePolicy::Ignore);
+    assert_eq!(options.keys, DEFAULT_LAYOUT);
+    assert_eq!(options.pitch, DEFAULT_PITCH);
+    assert_eq!(options.volume, DEFAULT_VOLUME);
+    assert!(!options.mute && !options.debug && !options.headless && !options.help);
+    assert_eq!((options.cycles, options.frames), (None, None));
+    assert_eq!((options.dump, options.trace, options.output), (None, None, None));
+}
+
+#[test]
+fn test_rom_argument() {
+    assert_eq!(parse(&[]).err(), Some("no ROM file given".to_string()));
+    assert_eq!(parse(&["a.ch8", "b.ch8"]).err(), Some("unexpected argument 'b.ch8'".to_string()));
+
+    // The ROM can come before or after the options.
+    assert_eq!(parse(&["--mute", "a.ch8"]).unwrap().rom, "a.ch8");
+    assert_eq!(parse(&["a.ch8", "--mute"]).unwrap().rom, "a.ch8");
+
+    // Help does not need a ROM.
+    assert!(parse(&["--help"]).unwrap().help);
+}
+
+#[test]
+fn test_commands() {
+    let options = parse(&["disasm", "a.ch8"]).unwrap();
+    assert_eq!(options.command, Command::Disasm);
+    assert_eq!(options.rom, "a.ch8");
+
+    let options = parse(&["asm", "a.asm", "-o", "out.ch8"]).unwrap();
+    assert_eq!(options.command, Command::Asm);
+    assert_eq!(options.output.as_deref(), Some("out.ch8"));
+
+    // A command is only recognized as the first argument.
+    assert_eq!(parse(&["a.ch8", "disasm"]).err(), Some("unexpected argument 'disasm'".to_string()));
+}
+
+#[test]
+fn test_value_flags() {
+    let options = parse(&[
+        "-s", "8", "-c", "1000", "-q", "schip", "--stack-depth", "12", "--on-unknown", "log",
+        "--on-sys", "trap", "-k", "1234QWERASDFZXCV", "-p", "880", "-v", "50", "a.ch8",
+    ]).unwrap();
+
+    assert_eq!(options.scale, 8);
+    assert_eq!(options.speed, 1000);
+    assert_eq!(options.quirks, "schip");
+    assert_eq!(options.stack_depth, 12);
+    assert_eq!(options.on_unknown, OpcodePolicy::Log);
+    assert_eq!(options.on_sys, OpcodePolicy::Trap);
+    assert_eq!(options.keys, "1234QWERASDFZXCV");
+    assert_eq!(options.pitch, 880);
+    assert_eq!(options.volume, 50);
+
+    let options = parse(&["--scale", "4", "--speed", "500", "--quirks", "vip", "--keys", "X", "a.ch8"]).unwrap();
+    assert_eq!((options.scale, options.speed), (4, 500));
+    assert_eq!((options.quirks.as_str(), options.keys.as_str()), ("vip", "X"));
+}
+
+#[test]
+fn test_switches() {
+    let options = parse(&["-m", "-d", "a.ch8"]).unwrap();
+    assert!(options.mute && options.debug && !options.headless);
+
+    let options = parse(&["--mute", "--debug", "--headless", "a.ch8"]).unwrap();
+    assert!(options.mute && options.debug && options.headless);
+}
+
+#[test]
+fn test_headless_flags() {
+    let options = parse(&["--headless", "--cycles", "20000", "--dump", "out.png", "a.ch8"]).unwrap();
+    assert_eq!(options.cycles, Some(20_000));
+    assert_eq!(options.dump.as_deref(), Some("out.png"));
+
+    let options = parse(&["--headless", "--frames", "60", "a.ch8"]).unwrap();
+    assert_eq!(options.frames, Some(60));
+}
+
+#[test]
+fn test_trace_flags() {
+    let options = parse(&[
+        "--trace", "t.log", "--trace-last", "100", "--trace-pc", "200-2FF", "--trace-op", "1,D", "a.ch8",
+    ]).unwrap();
+
+    assert_eq!(options.trace.as_deref(), Some("t.log"));
+    assert_eq!(options.trace_last, Some(100));
+    assert_eq!(options.trace_pc.as_deref(), Some("200-2FF"));
+    assert_eq!(options.trace_ops.as_deref(), Some("1,D"));
+}
+
+#[test]
+fn test_conflicting_flags() {
+    assert_eq!(
+        parse(&["--cycles", "10", "--frames", "10", "a.ch8"]).err(),
+        Some("options '--cycles' and '--frames' cannot be used together".to_string()),
+    );
+
+    for filter in [["--trace-last", "10"], ["--trace-pc", "200-2FF"], ["--trace-op", "D"]] {
+        assert_eq!(
+            parse(&[filter[0], filter[1], "a.ch8"]).err(),
+            Some("options '--trace-last', '--trace-pc' and '--trace-op' need '--trace'".to_string()),
+        );
+    }
+
+    assert_eq!(
+        parse(&["-o", "out.ch8", "a.ch8"]).err(),
+        Some("option '--output' can only be used with asm".to_string()),
+    );
+}
+
+#[test]
+fn test_invalid_values() {
+    assert_eq!(parse(&["--bogus", "a.ch8"]).err(), Some("unknown option '--bogus'".to_string()));
+    assert_eq!(parse(&["a.ch8", "--scale"]).err(), Some("option '--scale' needs a value".to_string()));
+    assert_eq!(
+        parse(&["--scale", "0", "a.ch8"]).err(),
+        Some("option '--scale' expects a positive number, got '0'".to_string()),
+    );
+    assert_eq!(
+        parse(&["--speed", "fast", "a.ch8"]).err(),
+        Some("option '--speed' expects a positive number, got 'fast'".to_string()),
+    );
+    assert_eq!(
+        parse(&["--volume", "101", "a.ch8"]).err(),
+        Some("volume must be between 1 and 100, got 101".to_string()),
+    );
+    assert_eq!(
+        parse(&["--on-sys", "panic", "a.ch8"]).err(),
+        Some("option '--on-sys' expects one of trap, log, ignore, got 'panic'".to_string()),
+    );
+}
@@ -1,51 +1,81 @@
-#[allow(dead_code)]
-pub struct Stack<T> {
-    tail: usize,
-    data: Vec<Option<T>>,
+use std::fmt;
+
+/// Number of return addresses the stack holds by default, as on most
+/// CHIP-8 interpreters. The COSMAC VIP had room for 12.
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+
+/// Errors from pushing to a full call stack or popping an empty one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackError {
+    Overflow,
+    Underflow,
 }
 
-#[allow(dead_code)]
-impl<T> Stack<T> {
-    pub fn new(size: usize) -> Self {
-        Stack {
-            tail: 0,
-            data: Vec::with_capacity(size),
+impl fmt::Display for StackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackError::Overflow => write!(f, "call stack is full"),
+            StackError::Underflow => write!(f, "call stack is empty"),
         }
     }
+}
 
-    pub fn push(&mut self, element: T) {
-        self.push_element(element);
+impl std::error::Error for StackError {}
 
-        if self.tail + 1 < self.data.capacity() {
-            self.tail += 1;
-        } else {
-            self.tail = 0;
+/// Fixed depth stack of subroutine return addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CallStack {
+    frames: Vec<usize>,
+    depth: usize,
+}
+
+#[allow(dead_code)]
+impl CallStack {
+    pub fn new(depth: usize) -> Self {
+        CallStack {
+            frames: Vec::with_capacity(depth),
+            depth,
         }
     }
 
-    pub fn pop(&mut self) -> Option<T> {
-        let prev = match self.tail {
-            0 => 0,
-            _ => {
-                self.tail -= 1;
-                self.tail
-            }
-        };
+    /// Pushes a return address, failing when the stack is already full.
+    pub fn push(&mut self, address: usize) -> Result<(), StackError> {
+        if self.frames.len() == self.depth {
+            return Err(StackError::Overflow);
+        }
 
-        self.data[prev].take()
+        self.frames.push(address);
+        Ok(())
     }
 
-    fn push_element(&mut self, element: T) {
-        if self.is_full() {
-            self.data.push(Some(element)); // grow the vec by pushing an an element
-        } else {
-            // we need to clean-up memory of the previous value by extracting it in the scope
-            self.data[self.tail].take();
-            self.data[self.tail] = Some(element);
-        }
+    /// Pops the most recent return address, failing when the stack is empty.
+    pub fn pop(&mut self) -> Result<usize, StackError> {
+        self.frames.pop().ok_or(StackError::Underflow)
     }
 
-    fn is_full(&self) -> bool {
-        self.tail == self.data.len() && self.tail < self.data.capacity()
+    /// Number of return addresses currently on the stack.
+    pub fn len(&self) -> usize {
+        self.frames.len()
     }
-}
\ No newline at end of file
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Most return addresses the stack can hold.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Return addresses currently on the stack, from the bottom up.
+    pub fn frames(&self) -> &[usize] {
+        &self.frames
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests;
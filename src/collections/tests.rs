@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn test_push_and_pop() {
+    let mut stack = CallStack::new(DEFAULT_STACK_DEPTH);
+
+    stack.push(0x202).unwrap();
+    stack.push(0x30A).unwrap();
+
+    assert_eq!(stack.frames(), [0x202, 0x30A]);
+    assert_eq!(stack.pop(), Ok(0x30A));
+    assert_eq!(stack.pop(), Ok(0x202));
+    assert!(stack.is_empty());
+}
+
+#[test]
+fn test_overflow() {
+    let mut stack = CallStack::new(2);
+
+    stack.push(1).unwrap();
+    stack.push(2).unwrap();
+
+    // A full stack is left as it was rather than wrapping around.
+    assert_eq!(stack.push(3), Err(StackError::Overflow));
+    assert_eq!(stack.frames(), [1, 2]);
+}
+
+#[test]
+fn test_underflow() {
+    let mut stack = CallStack::new(2);
+
+    assert_eq!(stack.pop(), Err(StackError::Underflow));
+
+    stack.push(1).unwrap();
+    stack.pop().unwrap();
+    assert_eq!(stack.pop(), Err(StackError::Underflow));
+}
@@ -0,0 +1,401 @@
+use std::io::{self, BufRead, Write};
+
+use crate::processor::breakpoints::{Access, Register};
+use crate::processor::processor::{Processor, MEM_SIZE};
+
+pub const HELP: &str = "\
+Commands:
+    s, step [N]          Execute the next N instructions (default 1)
+    n, next              Execute the next instruction, running CALLs until they return
+    c, continue          Run until a breakpoint is reached
+    b, break [ADDR]      Set a breakpoint on the address, or list all breakpoints
+                         and watchpoints
+    bo OPCODE [MASK]     Break before any opcode where opcode & MASK matches
+                         (default mask FFFF)
+    w, watch ADDR[-END] [r|w|rw]
+                         Break after memory in the range is read or written
+                         (default rw)
+    wr REG               Break after the register (V0 to VF or I) changes
+    d, delete ADDR       Remove the breakpoint on the address
+    clear                Remove every breakpoint and watchpoint
+    r, regs              Show the registers, timers and stack
+    bt, backtrace        Show the subroutine calls on the stack, innermost first
+    m, mem ADDR [LEN]    Show LEN bytes of memory starting at the address (default 16)
+    q, quit              Exit the emulator
+    h, help              Show this message
+
+An empty line repeats step. Addresses are hexadecimal.";
+
+/// What the frontend should do after a debugger command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Stay paused and ask for another command.
+    Pause,
+
+    /// Resume running the program.
+    Run,
+
+    /// Exit the emulator.
+    Quit,
+}
+
+/// Interactive step debugger driven by commands typed in the terminal.
+pub struct Debugger {
+    /// True while execution is stopped and the debugger is waiting for commands.
+    pub paused: bool,
+
+    /// Return address and stack depth we are running to while stepping over a CALL.
+    step_over: Option<(usize, usize)>,
+}
+
+impl Debugger {
+
+    pub fn new(paused: bool) -> Self {
+        Debugger {
+            paused,
+            step_over: None,
+        }
+    }
+
+    /// Checked before every instruction while running, returns true and pauses
+    /// when a breakpoint or watchpoint fired or a stepped over CALL has returned.
+    pub fn should_break(&mut self, processor: &mut Processor) -> bool {
+        if self.paused {
+            return true;
+        }
+
+        if let Some(reason) = processor.take_break() {
+            self.pause(processor, &reason.to_string());
+            return true;
+        }
+
+        if let Some((address, depth)) = self.step_over {
+            if processor.pc == address && processor.stack.len() <= depth {
+                self.step_over = None;
+                self.pause(processor, "stepped over call");
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Pauses execution and prints the reason along with the current state.
+    pub fn pause(&mut self, processor: &Processor, reason: &str) {
+        self.paused = true;
+        self.step_over = None;
+
+        println!("paused: {}", reason);
+        print_state(processor);
+    }
+
+    /// Reads one command from standard input and runs it. Reaching the end
+    /// of the input is treated as quit.
+    pub fn prompt(&mut self, processor: &mut Processor) -> Action {
+        print!("(debug) ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => Action::Quit,
+            Ok(_) => self.command(processor, &line).unwrap_or_else(|message| {
+                println!("error: {}", message);
+                Action::Pause
+            }),
+        }
+    }
+
+    /// Runs a single debugger command.
+    pub fn command(&mut self, processor: &mut Processor, line: &str) -> Result<Action, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("step");
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "s" | "step" => {
+                let count = match args.first() {
+                    Some(count) => count.parse::<u32>().map_err(|_| format!("invalid count '{}'", count))?,
+                    None => 1,
+                };
+
+                // The instruction paused at runs even if it has a breakpoint,
+                // stepping stops early at any breakpoint after it.
+                processor.resume();
+
+                for _ in 0..count {
+                    step_instruction(processor)?;
+
+                    if report_break(processor) {
+                        break;
+                    }
+                }
+
+                print_state(processor);
+                Ok(Action::Pause)
+            },
+
+            "n" | "next" => {
+                if is_call(processor) {
+                    self.step_over = Some((processor.pc + 2, processor.stack.len()));
+                    self.resume(processor);
+                    Ok(Action::Run)
+                } else {
+                    processor.resume();
+                    step_instruction(processor)?;
+                    report_break(processor);
+                    print_state(processor);
+                    Ok(Action::Pause)
+                }
+            },
+
+            "c" | "continue" => {
+                self.resume(processor);
+                Ok(Action::Run)
+            },
+
+            "b" | "break" => {
+                match args.first() {
+                    Some(address) => {
+                        let address = parse_address(address)?;
+
+                        processor.breakpoints.add_pc(address);
+                        println!("breakpoint set at {:#05X}", address);
+                    },
+                    None => print_breakpoints(processor),
+                }
+
+                Ok(Action::Pause)
+            },
+
+            "bo" => {
+                let value = parse_word(args.first().ok_or("bo needs an opcode")?)?;
+                let mask = match args.get(1) {
+                    Some(mask) => parse_word(mask)?,
+                    None => 0xFFFF,
+                };
+
+                processor.breakpoints.add_opcode(value, mask);
+                Ok(Action::Pause)
+            },
+
+            "w" | "watch" => {
+                let range = args.first().ok_or("watch needs an address")?;
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (parse_address(start)?, parse_address(end)?),
+                    None => (parse_address(range)?, parse_address(range)?),
+                };
+
+                let access = match args.get(1).copied() {
+                    Some("r") => Access::Read,
+                    Some("w") => Access::Write,
+                    Some("rw") | None => Access::ReadWrite,
+                    Some(other) => return Err(format!("invalid access '{}', expected r, w or rw", other)),
+                };
+
+                if start > end {
+                    return Err(format!("invalid range '{}'", range));
+                }
+
+                processor.breakpoints.watch_memory(start..=end, access);
+                Ok(Action::Pause)
+            },
+
+            "wr" => {
+                let register = parse_register(args.first().ok_or("wr needs a register")?)?;
+
+                processor.breakpoints.watch_register(register)?;
+                Ok(Action::Pause)
+            },
+
+            "d" | "delete" => {
+                let address = parse_address(args.first().ok_or("delete needs an address")?)?;
+
+                processor.breakpoints.remove_pc(address);
+                Ok(Action::Pause)
+            },
+
+            "clear" => {
+                processor.breakpoints.clear();
+                Ok(Action::Pause)
+            },
+
+            "r" | "regs" => {
+                print_state(processor);
+                Ok(Action::Pause)
+            },
+
+            "bt" | "backtrace" => {
+                print_backtrace(processor);
+                Ok(Action::Pause)
+            },
+
+            "m" | "mem" => {
+                let start = parse_address(args.first().ok_or("mem needs an address")?)?;
+                let len = match args.get(1) {
+                    Some(len) => len.parse::<usize>().map_err(|_| format!("invalid length '{}'", len))?,
+                    None => 16,
+                };
+                let end = start.checked_add(len).ok_or_else(|| format!("invalid length '{}'", len))?;
+
+                print_memory(processor, start, end.min(MEM_SIZE));
+                Ok(Action::Pause)
+            },
+
+            "q" | "quit" => Ok(Action::Quit),
+
+            "h" | "help" => {
+                println!("{}", HELP);
+                Ok(Action::Pause)
+            },
+
+            _ => Err(format!("unknown command '{}', type 'help' for a list of commands", command)),
+        }
+    }
+
+    /// Runs the program again from the instruction paused at, which is not
+    /// stopped at a second time by its own breakpoint.
+    fn resume(&mut self, processor: &mut Processor) {
+        self.paused = false;
+        processor.resume();
+    }
+}
+
+/// Runs the next instruction. After a draw with the display wait quirk the
+/// frame is ended first, as nothing runs until the next one while paused.
+fn step_instruction(processor: &mut Processor) -> Result<(), String> {
+    if processor.waiting_for_vblank() {
+        println!("waited for vblank");
+        processor.tick();
+    }
+
+    processor.cycle_cpu().map_err(|err| err.to_string())
+}
+
+/// Returns true if the instruction at the program counter is a CALL (2NNN).
+fn is_call(processor: &Processor) -> bool {
+    opcode_at(processor, processor.pc).is_some_and(|opcode| opcode >> 12 == 0x2)
+}
+
+/// Reads the opcode at the address, or None when it runs past the end of memory.
+fn opcode_at(processor: &Processor, address: usize) -> Option<u16> {
+    let bytes = processor.memory().get(address..address.checked_add(2)?)?;
+
+    Some((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
+
+/// Prints the breakpoint or watchpoint that fired while stepping, if any,
+/// and returns true if one did.
+fn report_break(processor: &mut Processor) -> bool {
+    match processor.take_break() {
+        Some(reason) => {
+            println!("hit: {}", reason);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Lists every breakpoint and watchpoint set on the processor.
+fn print_breakpoints(processor: &Processor) {
+    let breakpoints = &processor.breakpoints;
+
+    for address in &breakpoints.pc {
+        println!("breakpoint at {:#05X}", address);
+    }
+
+    for (value, mask) in &breakpoints.opcodes {
+        println!("opcode breakpoint on {:04X} mask {:04X}", value, mask);
+    }
+
+    for watchpoint in &breakpoints.memory {
+        println!(
+            "{:?} watchpoint on {:#05X}-{:#05X}",
+            watchpoint.access, watchpoint.range.start(), watchpoint.range.end(),
+        );
+    }
+
+    for register in &breakpoints.registers {
+        println!("watchpoint on {}", register);
+    }
+}
+
+/// Prints the registers, stack and the next instruction.
+fn print_state(processor: &Processor) {
+    print!("{}", processor.register_dump());
+    println!("stack: {:03X?}", processor.stack.frames());
+
+    match opcode_at(processor, processor.pc) {
+        Some(next) => println!("next: {:#05X}: {:04X}", processor.pc, next),
+        None => println!("next: {:#05X}: past the end of memory", processor.pc),
+    }
+}
+
+/// Prints a line for each call on the stack, from the innermost out. The
+/// call was made from the instruction before its return address.
+fn print_backtrace(processor: &Processor) {
+    let frames = processor.stack.frames();
+
+    if frames.is_empty() {
+        println!("no calls on the stack");
+    }
+
+    for (level, &address) in frames.iter().rev().enumerate() {
+        match address.checked_sub(2).and_then(|call| Some((call, opcode_at(processor, call)?))) {
+            Some((call, opcode)) => {
+                println!("#{} {:#05X}: {:04X}, returns to {:#05X}", level, call, opcode, address);
+            },
+            None => println!("#{} returns to {:#05X}, outside of memory", level, address),
+        }
+    }
+
+    println!("depth {} of {}", frames.len(), processor.stack.depth());
+}
+
+/// Prints memory from start up to end as rows of 8 bytes.
+fn print_memory(processor: &Processor, start: usize, end: usize) {
+    let memory = processor.memory();
+
+    for row in (start..end).step_by(8) {
+        let bytes: Vec<String> = memory[row..(row + 8).min(end)].iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+
+        println!("{:#05X}: {}", row, bytes.join(" "));
+    }
+}
+
+/// Parses a hexadecimal address, with or without the 0x prefix.
+fn parse_address(text: &str) -> Result<usize, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+
+    usize::from_str_radix(digits, 16)
+        .ok()
+        .filter(|&address| address < MEM_SIZE)
+        .ok_or_else(|| format!("invalid address '{}'", text))
+}
+
+/// Parses a hexadecimal 16 bit value, with or without the 0x prefix.
+fn parse_word(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid value '{}'", text))
+}
+
+/// Parses a register name, V0 to VF or I.
+fn parse_register(text: &str) -> Result<Register, String> {
+    let upper = text.to_ascii_uppercase();
+
+    if upper == "I" {
+        return Ok(Register::I);
+    }
+
+    upper.strip_prefix('V')
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+        .map(Register::V)
+        .ok_or_else(|| format!("invalid register '{}'", text))
+}
+
+#[cfg(test)]
+mod tests;
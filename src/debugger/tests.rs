@@ -0,0 +1,173 @@
+use crate::debugger::{Action, Debugger};
+use crate::processor::processor::{Processor, MEM_SIZE};
+use crate::screen::Screen;
+use crate::quirks::Quirks;
+
+// 6001 -> Set register V[0] to 0x01
+// 220A -> Call subroutine at 0x20A
+// 6102 -> Set register V[1] to 0x02
+// 1206 -> Jump to self
+// 0000 -> Padding
+// 7005 -> Add 0x05 to V[0]
+// 00EE -> Return from subroutine
+const PROGRAM: [u8; 14] = [0x60, 0x01, 0x22, 0x0A, 0x61, 0x02, 0x12, 0x06, 0x00, 0x00, 0x70, 0x05, 0x00, 0xEE];
+
+/// Runs the processor like the frontend does until the debugger pauses it.
+fn run_until_break(debugger: &mut Debugger, processor: &mut Processor) {
+    for _ in 0..100 {
+        if debugger.should_break(processor) {
+            return;
+        }
+
+        processor.cycle_cpu().unwrap();
+    }
+
+    panic!("debugger did not pause");
+}
+
+#[test]
+fn test_step() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    processor.load(PROGRAM.to_vec()).unwrap();
+
+    assert!(debugger.should_break(&mut processor));
+    assert_eq!(debugger.command(&mut processor, "step"), Ok(Action::Pause));
+    assert_eq!(processor.pc, 0x202);
+
+    // Stepping follows the call into the subroutine.
+    assert_eq!(debugger.command(&mut processor, "s 2"), Ok(Action::Pause));
+    assert_eq!(processor.pc, 0x20C);
+    assert_eq!(processor.V[0x0], 0x06);
+}
+
+#[test]
+fn test_step_over_call() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    processor.load(PROGRAM.to_vec()).unwrap();
+    debugger.command(&mut processor, "step").unwrap();
+
+    assert_eq!(debugger.command(&mut processor, "next"), Ok(Action::Run));
+    run_until_break(&mut debugger, &mut processor);
+
+    assert!(debugger.paused);
+    assert_eq!(processor.pc, 0x204);
+    assert_eq!(processor.V[0x0], 0x06);
+}
+
+#[test]
+fn test_continue_to_breakpoint() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    processor.load(PROGRAM.to_vec()).unwrap();
+
+    debugger.command(&mut processor, "break 20a").unwrap();
+    assert_eq!(debugger.command(&mut processor, "continue"), Ok(Action::Run));
+    run_until_break(&mut debugger, &mut processor);
+
+    assert_eq!(processor.pc, 0x20A);
+
+    assert_eq!(processor.V[0x0], 0x01);
+
+    // Continuing from the breakpoint runs the instruction it is on instead
+    // of stopping on it again straight away.
+    debugger.command(&mut processor, "b 206").unwrap();
+    debugger.command(&mut processor, "c").unwrap();
+    run_until_break(&mut debugger, &mut processor);
+
+    assert!(processor.breakpoints.pc.contains(&0x20A));
+    assert_eq!(processor.pc, 0x206);
+    assert_eq!(processor.V[0x0], 0x06);
+    assert_eq!(processor.V[0x1], 0x02);
+
+    // 0x206 jumps to itself, so the same breakpoint only fires again on its
+    // next hit, one instruction later.
+    let cycles = processor.cycles;
+
+    debugger.command(&mut processor, "c").unwrap();
+    run_until_break(&mut debugger, &mut processor);
+
+    assert_eq!(processor.pc, 0x206);
+    assert_eq!(processor.cycles, cycles + 1);
+}
+
+#[test]
+fn test_invalid_command() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    assert!(debugger.command(&mut processor, "jump 200").is_err());
+    assert!(debugger.command(&mut processor, "break xyz").is_err());
+    assert_eq!(debugger.command(&mut processor, "quit"), Ok(Action::Quit));
+}
+
+#[test]
+fn test_watch_register() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    processor.load(PROGRAM.to_vec()).unwrap();
+
+    debugger.command(&mut processor, "wr v1").unwrap();
+    debugger.command(&mut processor, "continue").unwrap();
+    run_until_break(&mut debugger, &mut processor);
+
+    // Execution stops right after the instruction that changed V[1].
+    assert_eq!(processor.pc, 0x206);
+    assert_eq!(processor.V[0x1], 0x02);
+    assert!(debugger.command(&mut processor, "wr v10").is_err());
+}
+
+#[test]
+fn test_inspect_end_of_memory() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    processor.load(PROGRAM.to_vec()).unwrap();
+    processor.pc = MEM_SIZE - 1;
+
+    // Only one byte of the next instruction is left, which is reported
+    // instead of crashing the debugger.
+    assert_eq!(debugger.command(&mut processor, "regs"), Ok(Action::Pause));
+    assert_eq!(debugger.command(&mut processor, "bt"), Ok(Action::Pause));
+    assert_eq!(debugger.command(&mut processor, "mem fff8 100"), Ok(Action::Pause));
+    assert!(debugger.command(&mut processor, &format!("mem 200 {}", usize::MAX)).is_err());
+    assert!(debugger.command(&mut processor, "next").is_err());
+}
+
+#[test]
+fn test_step_past_draw_with_display_wait() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+    let mut debugger = Debugger::new(true);
+
+    // D001 -> Draw a 1 byte sprite at V[0], V[0]
+    // 6102 -> Set register V[1] to 0x02
+    // 6203 -> Set register V[2] to 0x03
+    processor.quirks = Quirks::VIP;
+    processor.load(vec![0xD0, 0x01, 0x61, 0x02, 0x62, 0x03]).unwrap();
+    processor.delay_timer = 5;
+
+    debugger.command(&mut processor, "step").unwrap();
+    assert_eq!(processor.pc, 0x202);
+    assert!(processor.waiting_for_vblank());
+
+    // The wait for the next frame ends instead of the step doing nothing.
+    debugger.command(&mut processor, "step").unwrap();
+    assert_eq!(processor.pc, 0x204);
+    assert_eq!(processor.V[0x1], 0x02);
+    assert_eq!(processor.delay_timer, 4);
+
+    debugger.command(&mut processor, "next").unwrap();
+    assert_eq!(processor.pc, 0x206);
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeSet;
+
+use crate::instruction::{decode, Instruction};
+
+/// Address programs are loaded at.
+pub const ORIGIN: usize = 0x200;
+
+/// Most data bytes listed on a single line.
+const DATA_PER_LINE: usize = 8;
+
+/// Turns a ROM into an annotated listing, one instruction per line:
+///
+/// ```text
+/// 0x200: 6A02  LD VA, 0x02
+/// ```
+///
+/// Code is told apart from data by following every path the program can
+/// take from the entry point, through jumps, calls and skips. Bytes that
+/// are never reached are listed as data with db. Jump, call and LD I
+/// targets inside the ROM get a label. The address of the 4 byte XO-CHIP
+/// long index load is shown in the mnemonic only.
+pub fn disassemble(rom: &[u8]) -> String {
+    let code = find_code(rom);
+    let labels = find_labels(rom, &code);
+    let end = ORIGIN + rom.len();
+
+    let mut listing = String::new();
+    let mut address = ORIGIN;
+
+    while address < end {
+        if labels.contains(&address) {
+            listing.push_str(&format!("{}:\n", label(address)));
+        }
+
+        if code.contains(&address) {
+            let opcode = read_opcode(rom, address).unwrap();
+            let instruction = decode(opcode).unwrap();
+
+            let text = format(rom, address, &instruction, &labels);
+
+            listing.push_str(&format!("{:#05X}: {:04X}  {}\n", address, opcode, text));
+            address += instruction.size();
+            continue;
+        }
+
+        // Group data bytes up to the next instruction or label.
+        let mut bytes = Vec::new();
+
+        while address < end && bytes.len() < DATA_PER_LINE && !code.contains(&address) {
+            if !bytes.is_empty() && labels.contains(&address) {
+                break;
+            }
+
+            bytes.push(format!("{:#04X}", rom[address - ORIGIN]));
+            address += 1;
+        }
+
+        let start = address - bytes.len();
+        listing.push_str(&format!("{:#05X}:       db {}\n", start, bytes.join(", ")));
+    }
+
+    listing
+}
+
+/// Decodes the instruction at the address, None for invalid opcodes and
+/// instructions that run past the end of the ROM.
+fn read_instruction(rom: &[u8], address: usize) -> Option<Instruction> {
+    let instruction = decode(read_opcode(rom, address)?).ok()?;
+
+    if instruction.size() == 4 {
+        read_opcode(rom, address + 2)?;
+    }
+
+    Some(instruction)
+}
+
+/// Address the instruction at the address refers to, read from the
+/// following word for the long index load.
+fn target(rom: &[u8], address: usize, instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::LdILong => read_opcode(rom, address + 2).map(|nnnn| nnnn as usize),
+        _ => instruction.address().map(|nnn| nnn as usize),
+    }
+}
+
+/// Reads the big endian opcode at the address, None if it runs past the ROM.
+fn read_opcode(rom: &[u8], address: usize) -> Option<u16> {
+    let offset = address.checked_sub(ORIGIN)?;
+    let hi = *rom.get(offset)? as u16;
+    let lo = *rom.get(offset + 1)? as u16;
+
+    Some(hi << 8 | lo)
+}
+
+/// Follows the control flow from the entry point and returns the address of
+/// every instruction that can be reached.
+fn find_code(rom: &[u8]) -> BTreeSet<usize> {
+    let mut code = BTreeSet::new();
+    let mut pending = vec![ORIGIN];
+
+    while let Some(address) = pending.pop() {
+        if code.contains(&address) {
+            continue;
+        }
+
+        let instruction = match read_instruction(rom, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        code.insert(address);
+
+        match instruction {
+            Instruction::Jp(nnn) => pending.push(nnn as usize),
+            Instruction::Call(nnn) => {
+                pending.push(nnn as usize);
+                pending.push(address + 2);
+            },
+
+            // The target of a computed jump is not known, and neither
+            // returning, jumping nor exiting falls through.
+            Instruction::Ret | Instruction::JpV0(_) | Instruction::Exit => {},
+
+            // Skips jump over the whole next instruction, which may be 4 bytes long.
+            _ if instruction.is_skip() => {
+                let next = read_instruction(rom, address + 2).map_or(2, |next| next.size());
+
+                pending.push(address + 2);
+                pending.push(address + 2 + next);
+            },
+
+            _ => pending.push(address + instruction.size()),
+        }
+    }
+
+    code
+}
+
+/// Collects the addresses inside the ROM that instructions refer to. A
+/// target in the middle of a listed instruction cannot get a label, so it
+/// is left out and the instruction shows the raw address instead.
+fn find_labels(rom: &[u8], code: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let end = ORIGIN + rom.len();
+    let inside = instruction_interiors(rom, code);
+
+    code.iter()
+        .filter_map(|&address| read_instruction(rom, address).map(|instruction| (address, instruction)))
+        .filter(|(_, instruction)| !matches!(instruction, Instruction::Sys(_)))
+        .filter_map(|(address, instruction)| target(rom, address, &instruction))
+        .filter(|&address| (ORIGIN..end).contains(&address) && !inside.contains(&address))
+        .collect()
+}
+
+/// Addresses covered by the second and later bytes of the instructions the
+/// listing shows, walking the ROM the same way disassemble does. Code that
+/// starts inside another instruction overlaps it and is not listed.
+fn instruction_interiors(rom: &[u8], code: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let end = ORIGIN + rom.len();
+    let mut inside = BTreeSet::new();
+    let mut address = ORIGIN;
+
+    while address < end {
+        match code.contains(&address).then(|| read_instruction(rom, address)).flatten() {
+            Some(instruction) => {
+                inside.extend(address + 1..address + instruction.size());
+                address += instruction.size();
+            },
+            None => address += 1,
+        }
+    }
+
+    inside
+}
+
+fn label(address: usize) -> String {
+    format!("L{:03X}", address)
+}
+
+/// Formats the instruction, naming its target by label when it has one.
+fn format(rom: &[u8], address: usize, instruction: &Instruction, labels: &BTreeSet<usize>) -> String {
+    match target(rom, address, instruction) {
+        Some(target) if labels.contains(&target) => instruction.format_with_address(&label(target)),
+        Some(target) if instruction.size() == 4 => instruction.format_with_address(&format!("{:#06X}", target)),
+        _ => instruction.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,74 @@
+use crate::assembler::assemble;
+use crate::disassembler::disassemble;
+
+#[test]
+fn test_disassemble() {
+    // 6A02 -> LD VA, 0x02
+    // 2208 -> CALL 0x208
+    // 1204 -> JP 0x204, loops forever
+    // F0   -> Data, never executed
+    // A20C -> LD I, 0x20C
+    // 00EE -> RET
+    // 3C   -> Data, referenced by LD I
+    let rom = [0x6A, 0x02, 0x22, 0x07, 0x12, 0x04, 0xF0, 0xA2, 0x0B, 0x00, 0xEE, 0x3C];
+
+    let expected = "\
+0x200: 6A02  LD VA, 0x02
+0x202: 2207  CALL L207
+L204:
+0x204: 1204  JP L204
+0x206:       db 0xF0
+L207:
+0x207: A20B  LD I, L20B
+0x209: 00EE  RET
+L20B:
+0x20B:       db 0x3C
+";
+
+    assert_eq!(disassemble(&rom), expected);
+}
+
+#[test]
+fn test_skips_reach_both_paths() {
+    // 3000 -> SE V0, 0x00
+    // 1206 -> JP 0x206
+    // 00E0 -> CLS, only reached when the jump is skipped
+    // 1206 -> JP 0x206
+    let rom = [0x30, 0x00, 0x12, 0x06, 0x00, 0xE0, 0x12, 0x06];
+
+    let listing = disassemble(&rom);
+
+    assert!(listing.contains("0x204: 00E0  CLS"));
+    assert!(!listing.contains("db"));
+}
+
+/// Turns a listing back into assembler source by dropping the address and
+/// opcode columns.
+fn reassemble(listing: &str) -> Vec<u8> {
+    let source: Vec<&str> = listing.lines()
+        .map(|line| if line.ends_with(':') { line } else { &line[13..] })
+        .collect();
+
+    assemble(&source.join("\n")).unwrap()
+}
+
+#[test]
+fn test_round_trip() {
+    let rom = [0x6A, 0x02, 0x22, 0x07, 0x12, 0x04, 0xF0, 0xA2, 0x0B, 0x00, 0xEE, 0x3C];
+
+    assert_eq!(reassemble(&disassemble(&rom)), rom);
+}
+
+#[test]
+fn test_target_inside_instruction() {
+    // 1201 -> JP 0x201, into the middle of itself
+    // 00FD -> Data after the SYS 0x100 the jump lands on
+    let rom = [0x12, 0x01, 0x00, 0xFD];
+
+    let listing = disassemble(&rom);
+
+    // No label can be placed at 0x201, so the raw address is used.
+    assert!(listing.contains("0x200: 1201  JP 0x201"), "{}", listing);
+    assert!(!listing.contains("L201"));
+    assert_eq!(reassemble(&listing), rom);
+}
@@ -0,0 +1,22 @@
+use crate::framebuffer::{Framebuffer, Region};
+
+#[cfg(feature = "sdl")]
+mod sdl;
+
+#[cfg(feature = "sdl")]
+pub use sdl::SdlDisplay;
+
+/// Something that can show the framebuffer, such as a window. The core only
+/// talks to the presentation layer through this, so it builds without SDL.
+pub trait Display {
+
+    /// Shows the framebuffer, only the dirty region has changed since the
+    /// last call.
+    fn present(&mut self, framebuffer: &Framebuffer, dirty: Region) -> Result<(), String>;
+
+    /// Changes the title shown to the user, displays without one ignore it.
+    fn set_title(&mut self, _title: &str) {}
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,99 @@
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+use crate::framebuffer::{Framebuffer, Region};
+use crate::screen::PALETTE;
+
+use super::Display;
+
+/// Bytes in each RGB24 texture pixel.
+const BYTES_PER_PIXEL: usize = 3;
+
+/// Draws the framebuffer by copying it into a streaming texture the size of
+/// the CHIP-8 screen, which SDL stretches over the whole window.
+pub struct SdlDisplay<'a> {
+    canvas: &'a mut Canvas<Window>,
+    textures: &'a TextureCreator<WindowContext>,
+    texture: Option<Texture<'a>>,
+
+    /// RGB copy of the framebuffer the texture is updated from.
+    staging: Vec<u8>,
+    width: usize,
+    height: usize,
+
+    /// Only upload the part of the framebuffer that changed since the last
+    /// frame, rather than all of it.
+    pub dirty_rects: bool,
+}
+
+impl<'a> SdlDisplay<'a> {
+
+    pub fn new(canvas: &'a mut Canvas<Window>, textures: &'a TextureCreator<WindowContext>) -> SdlDisplay<'a> {
+        SdlDisplay { canvas, textures, texture: None, staging: Vec::new(), width: 0, height: 0, dirty_rects: true }
+    }
+
+    /// Fills the window before the first frame is drawn.
+    pub fn setup(&mut self) {
+        self.canvas.set_draw_color(Color::RGB(0, 255, 255));
+        self.canvas.clear();
+        self.canvas.present();
+    }
+
+}
+
+impl Display for SdlDisplay<'_> {
+
+    /// Uploads the changed region of the framebuffer and shows it.
+    fn present(&mut self, framebuffer: &Framebuffer, dirty: Region) -> Result<(), String> {
+        let (width, height) = (framebuffer.width(), framebuffer.height());
+        let full = Region { x: 0, y: 0, width, height };
+
+        // The texture is recreated when the resolution changes, which also
+        // needs the whole framebuffer uploaded.
+        let region = if self.texture.is_none() || (width, height) != (self.width, self.height) {
+            let texture = self.textures
+                .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                .map_err(|err| err.to_string())?;
+
+            self.texture = Some(texture);
+            self.staging = vec![0; width * height * BYTES_PER_PIXEL];
+            self.width = width;
+            self.height = height;
+            full
+        } else if self.dirty_rects {
+            dirty
+        } else {
+            full
+        };
+
+        for y in region.y..region.y + region.height {
+            for x in region.x..region.x + region.width {
+                let offset = (y * width + x) * BYTES_PER_PIXEL;
+                let (r, g, b) = PALETTE[framebuffer.get(x, y) & 3];
+
+                self.staging[offset..offset + BYTES_PER_PIXEL].copy_from_slice(&[r, g, b]);
+            }
+        }
+
+        // SDL reads each row of the rectangle a pitch apart, so the data
+        // starts at the first pixel of the region within the full copy.
+        let rect = Rect::new(region.x as i32, region.y as i32, region.width as u32, region.height as u32);
+        let start = (region.y * width + region.x) * BYTES_PER_PIXEL;
+        let texture = self.texture.as_mut().unwrap();
+
+        texture
+            .update(rect, &self.staging[start..], width * BYTES_PER_PIXEL)
+            .map_err(|err| err.to_string())?;
+
+        self.canvas.copy(texture, None, None)?;
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Changes the title of the window.
+    fn set_title(&mut self, title: &str) {
+        self.canvas.window_mut().set_title(title).ok();
+    }
+}
@@ -0,0 +1,53 @@
+use super::*;
+use crate::screen::Screen;
+
+/// Display that records the regions it was asked to present.
+struct RecordingDisplay {
+    presented: Vec<Region>,
+    title: Option<String>,
+}
+
+impl Display for RecordingDisplay {
+    fn present(&mut self, framebuffer: &Framebuffer, dirty: Region) -> Result<(), String> {
+        assert!(dirty.x + dirty.width <= framebuffer.width());
+        assert!(dirty.y + dirty.height <= framebuffer.height());
+
+        self.presented.push(dirty);
+        Ok(())
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = Some(title.to_string());
+    }
+}
+
+#[test]
+fn test_present_only_when_dirty() {
+    let mut display = RecordingDisplay { presented: Vec::new(), title: None };
+    let mut screen = Screen::new();
+
+    // A new screen has never been shown, so all of it is presented.
+    screen.present(&mut display).unwrap();
+    assert_eq!(display.presented, vec![Region { x: 0, y: 0, width: 64, height: 32 }]);
+
+    screen.present(&mut display).unwrap();
+    assert_eq!(display.presented.len(), 1);
+
+    screen.toggle_pixel(3, 4, 1);
+    screen.present(&mut display).unwrap();
+    assert_eq!(display.presented.len(), 2);
+    assert_eq!(display.presented[1], Region { x: 3, y: 4, width: 1, height: 1 });
+}
+
+#[test]
+fn test_present_through_trait_object() {
+    let mut display = RecordingDisplay { presented: Vec::new(), title: None };
+    let mut screen = Screen::new();
+
+    let dynamic: &mut dyn Display = &mut display;
+    dynamic.set_title("chip-8-rust");
+    screen.present(dynamic).unwrap();
+
+    assert_eq!(display.title.as_deref(), Some("chip-8-rust"));
+    assert_eq!(display.presented.len(), 1);
+}
@@ -0,0 +1,224 @@
+#![allow(dead_code)]
+
+/// Number of XO-CHIP bitplanes a framebuffer holds.
+pub const PLANES: usize = 2;
+
+/// Bits in each word of a packed row.
+const WORD_BITS: usize = 64;
+
+/// A rectangle of pixels that changed since the last time the framebuffer was drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+
+    /// Smallest region covering both regions.
+    pub fn union(self, other: Region) -> Region {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        Region { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+/// Packed framebuffer with one bit per pixel in each bitplane. Rows are
+/// stored as 64 bit words, the leftmost pixel in the most significant bit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+
+    /// Words in each row.
+    stride: usize,
+
+    planes: [Vec<u64>; PLANES],
+    dirty: Option<Region>,
+}
+
+impl Framebuffer {
+
+    /// A blank framebuffer, marked dirty so it is drawn the first time.
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        let stride = width.div_ceil(WORD_BITS);
+        let mut framebuffer = Framebuffer {
+            width,
+            height,
+            stride,
+            planes: std::array::from_fn(|_| vec![0; stride * height]),
+            dirty: None,
+        };
+
+        framebuffer.mark_all();
+        framebuffer
+    }
+
+    /// Builds a framebuffer from rows holding one value per pixel, with a
+    /// bit per bitplane.
+    pub fn from_rows(rows: &[Vec<usize>]) -> Framebuffer {
+        let width = rows.first().map_or(0, Vec::len);
+        let mut framebuffer = Framebuffer::new(width, rows.len());
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &v) in row.iter().enumerate().take(width) {
+                framebuffer.set(x, y, v);
+            }
+        }
+
+        framebuffer
+    }
+
+    /// Unpacks the framebuffer into rows holding one value per pixel.
+    pub fn to_rows(&self) -> Vec<Vec<usize>> {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| self.get(x, y)).collect())
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Word holding the pixel and the mask of its bit.
+    fn position(&self, x: usize, y: usize) -> (usize, u64) {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) is off the screen", x, y);
+
+        (y * self.stride + x / WORD_BITS, 1 << (WORD_BITS - 1 - x % WORD_BITS))
+    }
+
+    /// Value of the pixel, with a bit set for each plane it is lit in.
+    pub fn get(&self, x: usize, y: usize) -> usize {
+        let (word, bit) = self.position(x, y);
+
+        (0..PLANES).filter(|&plane| self.planes[plane][word] & bit != 0).map(|plane| 1 << plane).sum()
+    }
+
+    /// Sets the pixel in every plane from the bits of the value.
+    pub fn set(&mut self, x: usize, y: usize, v: usize) {
+        let (word, bit) = self.position(x, y);
+
+        for (plane, words) in self.planes.iter_mut().enumerate() {
+            if v & (1 << plane) != 0 {
+                words[word] |= bit;
+            } else {
+                words[word] &= !bit;
+            }
+        }
+
+        self.mark(Region { x, y, width: 1, height: 1 });
+    }
+
+    /// Flips the pixel in the planes of the mask.
+    pub fn toggle(&mut self, x: usize, y: usize, mask: usize) {
+        let (word, bit) = self.position(x, y);
+
+        for (plane, words) in self.planes.iter_mut().enumerate() {
+            if mask & (1 << plane) != 0 {
+                words[word] ^= bit;
+            }
+        }
+
+        self.mark(Region { x, y, width: 1, height: 1 });
+    }
+
+    /// Turns off every pixel in the planes of the mask.
+    pub fn clear(&mut self, mask: usize) {
+        for (plane, words) in self.planes.iter_mut().enumerate() {
+            if mask & (1 << plane) != 0 {
+                words.fill(0);
+            }
+        }
+
+        self.mark_all();
+    }
+
+    /// Moves the planes of the mask by dx columns and dy rows, the pixels
+    /// scrolled in are blank.
+    pub fn scroll(&mut self, dx: isize, dy: isize, mask: usize) {
+        let (width, height, stride) = (self.width, self.height, self.stride);
+
+        for (plane, words) in self.planes.iter_mut().enumerate() {
+            if mask & (1 << plane) == 0 {
+                continue;
+            }
+
+            let old = words.clone();
+
+            for y in 0..height {
+                let row = &mut words[y * stride..(y + 1) * stride];
+                let source_y = y as isize - dy;
+
+                if source_y < 0 || source_y >= height as isize {
+                    row.fill(0);
+                    continue;
+                }
+
+                let source = source_y as usize * stride;
+                shift_row(&old[source..source + stride], row, dx, width);
+            }
+        }
+
+        self.mark_all();
+    }
+
+    /// Number of lit bits across all the planes.
+    pub fn count_ones(&self) -> usize {
+        self.planes.iter().flatten().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Region that changed since the last call, if anything did.
+    pub fn take_dirty(&mut self) -> Option<Region> {
+        self.dirty.take()
+    }
+
+    fn mark(&mut self, region: Region) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(region),
+            None => region,
+        });
+    }
+
+    fn mark_all(&mut self) {
+        self.mark(Region { x: 0, y: 0, width: self.width, height: self.height });
+    }
+}
+
+/// Writes the row moved right by dx pixels, or left when dx is negative,
+/// into out. Bits past the width are cleared.
+fn shift_row(row: &[u64], out: &mut [u64], dx: isize, width: usize) {
+    let words = row.len() as isize;
+    let word_shift = dx.div_euclid(WORD_BITS as isize);
+    let bit_shift = dx.rem_euclid(WORD_BITS as isize) as u32;
+
+    let word = |i: isize| if (0..words).contains(&i) { row[i as usize] } else { 0 };
+
+    for (i, out) in out.iter_mut().enumerate() {
+        let source = i as isize - word_shift;
+
+        *out = if bit_shift == 0 {
+            word(source)
+        } else {
+            word(source) >> bit_shift | word(source - 1) << (WORD_BITS as u32 - bit_shift)
+        };
+    }
+
+    let tail = width % WORD_BITS;
+    if tail != 0 {
+        if let Some(last) = out.last_mut() {
+            *last &= !0 << (WORD_BITS - tail);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
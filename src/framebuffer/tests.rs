@@ -0,0 +1,114 @@
+use super::*;
+
+#[test]
+fn test_get_and_set() {
+    let mut framebuffer = Framebuffer::new(128, 64);
+
+    framebuffer.set(0, 0, 1);
+    framebuffer.set(63, 5, 2);
+    framebuffer.set(64, 5, 3);
+    framebuffer.set(127, 63, 1);
+
+    assert_eq!(framebuffer.get(0, 0), 1);
+    assert_eq!(framebuffer.get(1, 0), 0);
+    assert_eq!(framebuffer.get(63, 5), 2);
+    assert_eq!(framebuffer.get(64, 5), 3);
+    assert_eq!(framebuffer.get(127, 63), 1);
+    assert_eq!(framebuffer.count_ones(), 5);
+
+    framebuffer.set(64, 5, 0);
+    assert_eq!(framebuffer.get(64, 5), 0);
+}
+
+#[test]
+fn test_toggle_and_clear_planes() {
+    let mut framebuffer = Framebuffer::new(64, 32);
+
+    framebuffer.toggle(3, 4, 3);
+    assert_eq!(framebuffer.get(3, 4), 3);
+
+    framebuffer.toggle(3, 4, 1);
+    assert_eq!(framebuffer.get(3, 4), 2);
+
+    framebuffer.set(5, 5, 1);
+    framebuffer.clear(2);
+    assert_eq!(framebuffer.get(3, 4), 0);
+    assert_eq!(framebuffer.get(5, 5), 1);
+}
+
+#[test]
+fn test_scroll_across_words() {
+    let mut framebuffer = Framebuffer::new(128, 64);
+
+    framebuffer.set(62, 0, 1);
+    framebuffer.set(125, 1, 1);
+    framebuffer.scroll(4, 2, 1);
+
+    // The first pixel moves into the second word, the other falls off the edge.
+    assert_eq!(framebuffer.get(66, 2), 1);
+    assert_eq!(framebuffer.count_ones(), 1);
+
+    framebuffer.scroll(-66, -2, 1);
+    assert_eq!(framebuffer.get(0, 0), 1);
+    assert_eq!(framebuffer.count_ones(), 1);
+
+    framebuffer.scroll(0, -1, 1);
+    assert_eq!(framebuffer.count_ones(), 0);
+}
+
+#[test]
+fn test_scroll_keeps_width() {
+    let mut framebuffer = Framebuffer::new(10, 2);
+
+    framebuffer.set(9, 0, 1);
+    framebuffer.scroll(1, 0, 1);
+    assert_eq!(framebuffer.count_ones(), 0);
+
+    framebuffer.set(0, 1, 1);
+    framebuffer.scroll(9, 0, 1);
+    assert_eq!(framebuffer.get(9, 1), 1);
+}
+
+#[test]
+fn test_scroll_selected_plane() {
+    let mut framebuffer = Framebuffer::new(64, 32);
+
+    framebuffer.set(0, 0, 3);
+    framebuffer.scroll(1, 0, 2);
+
+    assert_eq!(framebuffer.get(0, 0), 1);
+    assert_eq!(framebuffer.get(1, 0), 2);
+}
+
+#[test]
+fn test_dirty_region() {
+    let mut framebuffer = Framebuffer::new(64, 32);
+
+    // A new framebuffer has to be drawn once.
+    assert_eq!(framebuffer.take_dirty(), Some(Region { x: 0, y: 0, width: 64, height: 32 }));
+    assert_eq!(framebuffer.take_dirty(), None);
+
+    framebuffer.set(10, 3, 1);
+    framebuffer.toggle(4, 8, 1);
+    assert_eq!(framebuffer.take_dirty(), Some(Region { x: 4, y: 3, width: 7, height: 6 }));
+
+    framebuffer.scroll(0, 1, 1);
+    assert_eq!(framebuffer.take_dirty(), Some(Region { x: 0, y: 0, width: 64, height: 32 }));
+}
+
+#[test]
+fn test_rows_round_trip() {
+    let mut framebuffer = Framebuffer::new(128, 64);
+
+    framebuffer.set(1, 2, 1);
+    framebuffer.set(100, 60, 3);
+
+    let rows = framebuffer.to_rows();
+    assert_eq!(rows.len(), 64);
+    assert_eq!(rows[60][100], 3);
+
+    let mut copy = Framebuffer::from_rows(&rows);
+    copy.take_dirty();
+    framebuffer.take_dirty();
+    assert_eq!(copy, framebuffer);
+}
@@ -0,0 +1,43 @@
+use std::time::Instant;
+
+use crate::processor::error::EmuError;
+use crate::processor::processor::Processor;
+use crate::scheduler::Scheduler;
+
+/// Number of frames a headless run lasts when no length is given, 10 seconds.
+pub const DEFAULT_FRAMES: u64 = 600;
+
+/// Number of frames to run for the limits given on the command line. A
+/// cycle limit alone runs as many frames as it takes to reach it, the
+/// default length only applies when neither limit is given.
+pub fn frame_limit(frames: Option<u64>, cycles: Option<u64>) -> u64 {
+    match (frames, cycles) {
+        (Some(frames), _) => frames,
+        (None, Some(_)) => u64::MAX,
+        (None, None) => DEFAULT_FRAMES,
+    }
+}
+
+/// Runs the program for the given number of frames at the given speed,
+/// stopping early once the processor has executed max_cycles instructions
+/// or the program has exited.
+pub fn run_frames(processor: &mut Processor, speed: u32, frames: u64, max_cycles: Option<u64>) -> Result<(), EmuError> {
+    let mut scheduler = Scheduler::new(speed, Instant::now());
+
+    for _ in 0..frames {
+        for _ in 0..scheduler.next_frame_instructions() {
+            if processor.halted || max_cycles.is_some_and(|cycles| processor.cycles >= cycles) {
+                return Ok(());
+            }
+
+            processor.cycle_cpu()?;
+        }
+
+        processor.tick();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
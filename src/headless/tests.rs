@@ -0,0 +1,31 @@
+use super::*;
+use crate::scheduler::DEFAULT_SPEED;
+use crate::screen::Screen;
+
+#[test]
+fn test_frame_limit() {
+    assert_eq!(frame_limit(None, None), DEFAULT_FRAMES);
+    assert_eq!(frame_limit(Some(10), None), 10);
+    assert_eq!(frame_limit(None, Some(20_000)), u64::MAX);
+}
+
+#[test]
+fn test_cycles_beyond_default_frames() {
+    let mut screen = Screen::new();
+    let mut processor = Processor::new(&mut screen);
+
+    // JP 0x200, loops forever.
+    processor.load(vec![0x12, 0x00]).unwrap();
+
+    // The default 600 frames only last about 7000 instructions at the
+    // default speed, a larger cycle count still has to be reached.
+    run_frames(&mut processor, DEFAULT_SPEED, frame_limit(None, Some(20_000)), Some(20_000)).unwrap();
+    assert_eq!(processor.cycles, 20_000);
+
+    let mut screen = Screen::new();
+    let mut processor = Processor::new(&mut screen);
+
+    processor.load(vec![0x12, 0x00]).unwrap();
+    run_frames(&mut processor, DEFAULT_SPEED, frame_limit(None, None), None).unwrap();
+    assert!(processor.cycles < 20_000);
+}
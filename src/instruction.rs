@@ -0,0 +1,446 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+/// A decoded CHIP-8 instruction. Registers are stored as their index into V,
+/// addresses as the 12 bit value from the opcode.
+///
+/// Mnemonics follow the conventions of Cowgod's technical reference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Instruction {
+    /// 0NNN - Call a machine code routine, ignored by modern interpreters.
+    Sys(u16),
+
+    /// 00E0
+    Cls,
+
+    /// 00EE
+    Ret,
+
+    /// 00CN - SUPER-CHIP, scroll the screen down N rows.
+    Scd(u8),
+
+    /// 00DN - XO-CHIP, scroll the screen up N rows.
+    Scu(u8),
+
+    /// 00FB - SUPER-CHIP, scroll the screen right 4 pixels.
+    Scr,
+
+    /// 00FC - SUPER-CHIP, scroll the screen left 4 pixels.
+    Scl,
+
+    /// 00FD - SUPER-CHIP, exit the interpreter.
+    Exit,
+
+    /// 00FE - SUPER-CHIP, switch to the 64x32 resolution.
+    Low,
+
+    /// 00FF - SUPER-CHIP, switch to the 128x64 resolution.
+    High,
+
+    /// 1NNN
+    Jp(u16),
+
+    /// 2NNN
+    Call(u16),
+
+    /// 3XNN
+    SeByte(u8, u8),
+
+    /// 4XNN
+    SneByte(u8, u8),
+
+    /// 5XY0
+    SeReg(u8, u8),
+
+    /// 5XY2 - XO-CHIP, store V[x] to V[y] in memory starting at I.
+    LdIRange(u8, u8),
+
+    /// 5XY3 - XO-CHIP, load V[x] to V[y] from memory starting at I.
+    LdRangeI(u8, u8),
+
+    /// 6XNN
+    LdByte(u8, u8),
+
+    /// 7XNN
+    AddByte(u8, u8),
+
+    /// 8XY0
+    LdReg(u8, u8),
+
+    /// 8XY1
+    Or(u8, u8),
+
+    /// 8XY2
+    And(u8, u8),
+
+    /// 8XY3
+    Xor(u8, u8),
+
+    /// 8XY4
+    AddReg(u8, u8),
+
+    /// 8XY5
+    Sub(u8, u8),
+
+    /// 8XY6
+    Shr(u8, u8),
+
+    /// 8XY7
+    Subn(u8, u8),
+
+    /// 8XYE
+    Shl(u8, u8),
+
+    /// 9XY0
+    SneReg(u8, u8),
+
+    /// ANNN
+    LdI(u16),
+
+    /// BNNN
+    JpV0(u16),
+
+    /// CXNN
+    Rnd(u8, u8),
+
+    /// DXYN
+    Drw(u8, u8, u8),
+
+    /// EX9E
+    Skp(u8),
+
+    /// EXA1
+    Sknp(u8),
+
+    /// F000 NNNN - XO-CHIP, load a 16 bit address into I. The address is the
+    /// word following the opcode, so the instruction is 4 bytes long.
+    LdILong,
+
+    /// FN01 - XO-CHIP, select the bitplanes drawn to, N is a mask.
+    Plane(u8),
+
+    /// F002 - XO-CHIP, load the 16 byte audio pattern from I.
+    Audio,
+
+    /// FX07
+    LdVxDt(u8),
+
+    /// FX0A
+    LdVxK(u8),
+
+    /// FX15
+    LdDtVx(u8),
+
+    /// FX18
+    LdStVx(u8),
+
+    /// FX1E
+    AddI(u8),
+
+    /// FX29
+    LdF(u8),
+
+    /// FX30 - SUPER-CHIP, point I at the large font sprite for V[x].
+    LdHf(u8),
+
+    /// FX33
+    LdB(u8),
+
+    /// FX3A - XO-CHIP, set the audio pattern playback pitch to V[x].
+    Pitch(u8),
+
+    /// FX55
+    LdIVx(u8),
+
+    /// FX65
+    LdVxI(u8),
+
+    /// FX75 - SUPER-CHIP, store V0 to V[x] in the RPL user flags.
+    LdRVx(u8),
+
+    /// FX85 - SUPER-CHIP, load V0 to V[x] from the RPL user flags.
+    LdVxR(u8),
+}
+
+/// Returned when an opcode is not part of the instruction set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown opcode {:04X}", self.opcode)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decodes an opcode into an instruction.
+pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+    use Instruction::*;
+
+    // Tuple for each nibble value present in our opcode
+    let nibbles: (u8, u8, u8, u8) = (
+        ((opcode & 0xF000) >> 12) as u8,
+        ((opcode & 0x0F00) >> 8) as u8,
+        ((opcode & 0x00F0) >> 4) as u8,
+        (opcode & 0x000F) as u8,
+    );
+
+    // Read the potential input from the instruction based on the defined
+    // Chip-8 conventions.
+    let nnn: u16 = opcode & 0x0FFF;
+    let nn: u8 = (opcode & 0x00FF) as u8;
+    let x: u8 = nibbles.1;
+    let y: u8 = nibbles.2;
+    let n: u8 = nibbles.3;
+
+    let instruction = match nibbles {
+        (0x0, 0x0, 0xE, 0x0)        => Cls,
+        (0x0, 0x0, 0xE, 0xE)        => Ret,
+        (0x0, 0x0, 0xC, _)          => Scd(n),
+        (0x0, 0x0, 0xD, _)          => Scu(n),
+        (0x0, 0x0, 0xF, 0xB)        => Scr,
+        (0x0, 0x0, 0xF, 0xC)        => Scl,
+        (0x0, 0x0, 0xF, 0xD)        => Exit,
+        (0x0, 0x0, 0xF, 0xE)        => Low,
+        (0x0, 0x0, 0xF, 0xF)        => High,
+        (0x0, _, _, _)              => Sys(nnn),
+        (0x1, _, _, _)              => Jp(nnn),
+        (0x2, _, _, _)              => Call(nnn),
+        (0x3, _, _, _)              => SeByte(x, nn),
+        (0x4, _, _, _)              => SneByte(x, nn),
+        (0x5, _, _, 0x0)            => SeReg(x, y),
+        (0x5, _, _, 0x2)            => LdIRange(x, y),
+        (0x5, _, _, 0x3)            => LdRangeI(x, y),
+        (0x6, _, _, _)              => LdByte(x, nn),
+        (0x7, _, _, _)              => AddByte(x, nn),
+        (0x8, _, _, 0x0)            => LdReg(x, y),
+        (0x8, _, _, 0x1)            => Or(x, y),
+        (0x8, _, _, 0x2)            => And(x, y),
+        (0x8, _, _, 0x3)            => Xor(x, y),
+        (0x8, _, _, 0x4)            => AddReg(x, y),
+        (0x8, _, _, 0x5)            => Sub(x, y),
+        (0x8, _, _, 0x6)            => Shr(x, y),
+        (0x8, _, _, 0x7)            => Subn(x, y),
+        (0x8, _, _, 0xE)            => Shl(x, y),
+        (0x9, _, _, 0x0)            => SneReg(x, y),
+        (0xA, _, _, _)              => LdI(nnn),
+        (0xB, _, _, _)              => JpV0(nnn),
+        (0xC, _, _, _)              => Rnd(x, nn),
+        (0xD, _, _, _)              => Drw(x, y, n),
+        (0xE, _, 0x9, 0xE)          => Skp(x),
+        (0xE, _, 0xA, 0x1)          => Sknp(x),
+        (0xF, 0x0, 0x0, 0x0)        => LdILong,
+        (0xF, _, 0x0, 0x1)          => Plane(x),
+        (0xF, 0x0, 0x0, 0x2)        => Audio,
+        (0xF, _, 0x0, 0x7)          => LdVxDt(x),
+        (0xF, _, 0x0, 0xA)          => LdVxK(x),
+        (0xF, _, 0x1, 0x5)          => LdDtVx(x),
+        (0xF, _, 0x1, 0x8)          => LdStVx(x),
+        (0xF, _, 0x1, 0xE)          => AddI(x),
+        (0xF, _, 0x2, 0x9)          => LdF(x),
+        (0xF, _, 0x3, 0x0)          => LdHf(x),
+        (0xF, _, 0x3, 0x3)          => LdB(x),
+        (0xF, _, 0x3, 0xA)          => Pitch(x),
+        (0xF, _, 0x5, 0x5)          => LdIVx(x),
+        (0xF, _, 0x6, 0x5)          => LdVxI(x),
+        (0xF, _, 0x7, 0x5)          => LdRVx(x),
+        (0xF, _, 0x8, 0x5)          => LdVxR(x),
+        _ => return Err(DecodeError { opcode }),
+    };
+
+    Ok(instruction)
+}
+
+/// Encodes an instruction back into its opcode, the inverse of decode.
+/// Register indexes and operands are masked to the width of their field.
+pub fn encode(instruction: &Instruction) -> u16 {
+    use Instruction::*;
+
+    let xy = |op: u16, x: u8, y: u8, n: u16| op | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | n;
+    let xnn = |op: u16, x: u8, nn: u8| op | (x as u16 & 0xF) << 8 | nn as u16;
+    let x = |op: u16, x: u8| op | (x as u16 & 0xF) << 8;
+
+    match *instruction {
+        Sys(nnn)        => nnn & 0x0FFF,
+        Cls             => 0x00E0,
+        Ret             => 0x00EE,
+        Scd(n)          => 0x00C0 | (n as u16 & 0xF),
+        Scu(n)          => 0x00D0 | (n as u16 & 0xF),
+        Scr             => 0x00FB,
+        Scl             => 0x00FC,
+        Exit            => 0x00FD,
+        Low             => 0x00FE,
+        High            => 0x00FF,
+        Jp(nnn)         => 0x1000 | (nnn & 0x0FFF),
+        Call(nnn)       => 0x2000 | (nnn & 0x0FFF),
+        SeByte(vx, nn)  => xnn(0x3000, vx, nn),
+        SneByte(vx, nn) => xnn(0x4000, vx, nn),
+        SeReg(vx, vy)   => xy(0x5000, vx, vy, 0x0),
+        LdIRange(vx, vy) => xy(0x5000, vx, vy, 0x2),
+        LdRangeI(vx, vy) => xy(0x5000, vx, vy, 0x3),
+        LdByte(vx, nn)  => xnn(0x6000, vx, nn),
+        AddByte(vx, nn) => xnn(0x7000, vx, nn),
+        LdReg(vx, vy)   => xy(0x8000, vx, vy, 0x0),
+        Or(vx, vy)      => xy(0x8000, vx, vy, 0x1),
+        And(vx, vy)     => xy(0x8000, vx, vy, 0x2),
+        Xor(vx, vy)     => xy(0x8000, vx, vy, 0x3),
+        AddReg(vx, vy)  => xy(0x8000, vx, vy, 0x4),
+        Sub(vx, vy)     => xy(0x8000, vx, vy, 0x5),
+        Shr(vx, vy)     => xy(0x8000, vx, vy, 0x6),
+        Subn(vx, vy)    => xy(0x8000, vx, vy, 0x7),
+        Shl(vx, vy)     => xy(0x8000, vx, vy, 0xE),
+        SneReg(vx, vy)  => xy(0x9000, vx, vy, 0x0),
+        LdI(nnn)        => 0xA000 | (nnn & 0x0FFF),
+        JpV0(nnn)       => 0xB000 | (nnn & 0x0FFF),
+        Rnd(vx, nn)     => xnn(0xC000, vx, nn),
+        Drw(vx, vy, n)  => xy(0xD000, vx, vy, n as u16 & 0xF),
+        Skp(vx)         => x(0xE09E, vx),
+        Sknp(vx)        => x(0xE0A1, vx),
+        LdILong         => 0xF000,
+        Plane(n)        => x(0xF001, n),
+        Audio           => 0xF002,
+        LdVxDt(vx)      => x(0xF007, vx),
+        LdVxK(vx)       => x(0xF00A, vx),
+        LdDtVx(vx)      => x(0xF015, vx),
+        LdStVx(vx)      => x(0xF018, vx),
+        AddI(vx)        => x(0xF01E, vx),
+        LdF(vx)         => x(0xF029, vx),
+        LdHf(vx)        => x(0xF030, vx),
+        LdB(vx)         => x(0xF033, vx),
+        Pitch(vx)       => x(0xF03A, vx),
+        LdIVx(vx)       => x(0xF055, vx),
+        LdVxI(vx)       => x(0xF065, vx),
+        LdRVx(vx)       => x(0xF075, vx),
+        LdVxR(vx)       => x(0xF085, vx),
+    }
+}
+
+impl Instruction {
+
+    /// Opcode of the instruction, see encode.
+    pub fn encode(&self) -> u16 {
+        encode(self)
+    }
+
+    /// Address the instruction refers to, for jumps, calls and LD I.
+    pub fn address(&self) -> Option<u16> {
+        match *self {
+            Instruction::Sys(nnn)
+            | Instruction::Jp(nnn)
+            | Instruction::Call(nnn)
+            | Instruction::LdI(nnn)
+            | Instruction::JpV0(nnn) => Some(nnn),
+            _ => None,
+        }
+    }
+
+    /// Length of the instruction in bytes, 4 for the XO-CHIP long index load
+    /// and 2 for everything else.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+
+    /// True for instructions that may skip the instruction that follows them.
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::SeByte(..)
+                | Instruction::SneByte(..)
+                | Instruction::SeReg(..)
+                | Instruction::SneReg(..)
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_)
+        )
+    }
+
+    /// Formats the instruction, writing the address operand with the given text.
+    /// Used by the disassembler to show labels instead of raw addresses.
+    pub fn format_with_address(&self, address: &str) -> String {
+        match self {
+            Instruction::Sys(_) => format!("SYS {}", address),
+            Instruction::LdILong => format!("LD I, LONG {}", address),
+            Instruction::Jp(_) => format!("JP {}", address),
+            Instruction::Call(_) => format!("CALL {}", address),
+            Instruction::LdI(_) => format!("LD I, {}", address),
+            Instruction::JpV0(_) => format!("JP V0, {}", address),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl TryFrom<u16> for Instruction {
+    type Error = DecodeError;
+
+    fn try_from(opcode: u16) -> Result<Self, Self::Error> {
+        decode(opcode)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            Sys(_) | Jp(_) | Call(_) | LdI(_) | JpV0(_) => {
+                let address = format!("{:#05X}", self.address().unwrap());
+                write!(f, "{}", self.format_with_address(&address))
+            },
+            Cls             => write!(f, "CLS"),
+            Ret             => write!(f, "RET"),
+            Scd(n)          => write!(f, "SCD {}", n),
+            Scu(n)          => write!(f, "SCU {}", n),
+            Scr             => write!(f, "SCR"),
+            Scl             => write!(f, "SCL"),
+            Exit            => write!(f, "EXIT"),
+            Low             => write!(f, "LOW"),
+            High            => write!(f, "HIGH"),
+            SeByte(x, nn)   => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            SneByte(x, nn)  => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            SeReg(x, y)     => write!(f, "SE V{:X}, V{:X}", x, y),
+            LdIRange(x, y)  => write!(f, "LD [I], V{:X}-V{:X}", x, y),
+            LdRangeI(x, y)  => write!(f, "LD V{:X}-V{:X}, [I]", x, y),
+            LdILong         => write!(f, "LD I, LONG"),
+            Plane(n)        => write!(f, "PLANE {}", n),
+            Audio           => write!(f, "AUDIO"),
+            LdByte(x, nn)   => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            AddByte(x, nn)  => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            LdReg(x, y)     => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or(x, y)        => write!(f, "OR V{:X}, V{:X}", x, y),
+            And(x, y)       => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor(x, y)       => write!(f, "XOR V{:X}, V{:X}", x, y),
+            AddReg(x, y)    => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub(x, y)       => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Shr(x, y)       => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Subn(x, y)      => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Shl(x, y)       => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SneReg(x, y)    => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Rnd(x, nn)      => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Drw(x, y, n)    => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Skp(x)          => write!(f, "SKP V{:X}", x),
+            Sknp(x)         => write!(f, "SKNP V{:X}", x),
+            LdVxDt(x)       => write!(f, "LD V{:X}, DT", x),
+            LdVxK(x)        => write!(f, "LD V{:X}, K", x),
+            LdDtVx(x)       => write!(f, "LD DT, V{:X}", x),
+            LdStVx(x)       => write!(f, "LD ST, V{:X}", x),
+            AddI(x)         => write!(f, "ADD I, V{:X}", x),
+            LdF(x)          => write!(f, "LD F, V{:X}", x),
+            LdHf(x)         => write!(f, "LD HF, V{:X}", x),
+            LdB(x)          => write!(f, "LD B, V{:X}", x),
+            Pitch(x)        => write!(f, "PITCH V{:X}", x),
+            LdIVx(x)        => write!(f, "LD [I], V{:X}", x),
+            LdVxI(x)        => write!(f, "LD V{:X}, [I]", x),
+            LdRVx(x)        => write!(f, "LD R, V{:X}", x),
+            LdVxR(x)        => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,86 @@
+use crate::instruction::{decode, encode, DecodeError, Instruction};
+
+#[test]
+fn test_decode() {
+    assert_eq!(decode(0x00E0), Ok(Instruction::Cls));
+    assert_eq!(decode(0x0123), Ok(Instruction::Sys(0x123)));
+    assert_eq!(decode(0x6A02), Ok(Instruction::LdByte(0xA, 0x02)));
+    assert_eq!(decode(0xD125), Ok(Instruction::Drw(0x1, 0x2, 0x5)));
+    assert_eq!(decode(0xF365), Ok(Instruction::LdVxI(0x3)));
+    assert_eq!(decode(0x5121), Err(DecodeError { opcode: 0x5121 }));
+    assert_eq!(decode(0xE1FF), Err(DecodeError { opcode: 0xE1FF }));
+}
+
+#[test]
+fn test_encode() {
+    assert_eq!(encode(&Instruction::Cls), 0x00E0);
+    assert_eq!(encode(&Instruction::Jp(0x228)), 0x1228);
+    assert_eq!(encode(&Instruction::Shl(0x3, 0x4)), 0x834E);
+    assert_eq!(encode(&Instruction::Skp(0xB)), 0xEB9E);
+    assert_eq!(Instruction::LdB(0x6).encode(), 0xF633);
+
+    // Fields are masked to their width.
+    assert_eq!(encode(&Instruction::LdI(0xFABC)), 0xAABC);
+}
+
+#[test]
+fn test_mnemonics() {
+    assert_eq!(decode(0x6A02).unwrap().to_string(), "LD VA, 0x02");
+    assert_eq!(decode(0x1228).unwrap().to_string(), "JP 0x228");
+    assert_eq!(decode(0xD01F).unwrap().to_string(), "DRW V0, V1, 15");
+    assert_eq!(decode(0xF255).unwrap().to_string(), "LD [I], V2");
+    assert_eq!(decode(0x8AB6).unwrap().to_string(), "SHR VA, VB");
+    assert_eq!(decode(0x00C4).unwrap().to_string(), "SCD 4");
+    assert_eq!(decode(0x00FF).unwrap().to_string(), "HIGH");
+    assert_eq!(decode(0xF330).unwrap().to_string(), "LD HF, V3");
+    assert_eq!(decode(0xF785).unwrap().to_string(), "LD V7, R");
+    assert_eq!(decode(0x5253).unwrap().to_string(), "LD V2-V5, [I]");
+    assert_eq!(decode(0xF201).unwrap().to_string(), "PLANE 2");
+    assert_eq!(decode(0xF000).unwrap().format_with_address("0x1234"), "LD I, LONG 0x1234");
+    assert_eq!(decode(0xF000).unwrap().size(), 4);
+    assert_eq!(DecodeError { opcode: 0x5121 }.to_string(), "unknown opcode 5121");
+}
+
+#[test]
+fn test_round_trip_every_opcode() {
+    let mut valid = 0;
+
+    for opcode in 0..=u16::MAX {
+        if let Ok(instruction) = decode(opcode) {
+            assert_eq!(encode(&instruction), opcode, "{:04X} decoded to {:?}", opcode, instruction);
+            valid += 1;
+        }
+    }
+
+    // 0NNN, 1NNN to 4XNN, 6XNN, 7XNN and ANNN to DXYN each cover 4096 opcodes,
+    // 5XY0, 5XY2, 5XY3 and 9XY0 cover 256, the nine 8XY_ forms 256 each, the two
+    // EX__ and fourteen FX__ forms 16 each, and F000 and F002 one each. The
+    // SUPER-CHIP and XO-CHIP 00__ forms are part of 0NNN.
+    assert_eq!(valid, 11 * 4096 + 4 * 256 + 9 * 256 + 16 * 16 + 2);
+}
+
+#[test]
+fn test_round_trip_every_instruction() {
+    use Instruction::*;
+
+    for x in 0..16u8 {
+        for y in 0..16u8 {
+            let nn = x << 4 | y;
+            let nnn = (x as u16) << 8 | nn as u16;
+
+            let instructions = [
+                Jp(nnn), Call(nnn), SeByte(x, nn), SneByte(x, nn), SeReg(x, y), LdByte(x, nn),
+                AddByte(x, nn), LdReg(x, y), Or(x, y), And(x, y), Xor(x, y), AddReg(x, y),
+                Sub(x, y), Shr(x, y), Subn(x, y), Shl(x, y), SneReg(x, y), LdI(nnn), JpV0(nnn),
+                Rnd(x, nn), Drw(x, y, x), Skp(x), Sknp(x), LdVxDt(x), LdVxK(x), LdDtVx(x),
+                LdStVx(x), AddI(x), LdF(x), LdB(x), LdIVx(x), LdVxI(x), Scd(x), Scr, Scl,
+                Exit, Low, High, LdHf(x), LdRVx(x), LdVxR(x), Scu(x), LdIRange(x, y),
+                LdRangeI(x, y), LdILong, Plane(x), Audio, Pitch(x),
+            ];
+
+            for instruction in instructions {
+                assert_eq!(decode(encode(&instruction)), Ok(instruction));
+            }
+        }
+    }
+}
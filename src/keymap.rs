@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use sdl2::keyboard::Keycode;
+
+use chip_8_rust::keypad::KEY_COUNT;
+
+use crate::cli::DEFAULT_LAYOUT;
+
+/// Maps SDL keyboard keys onto the 16 key hexadecimal keypad.
+pub struct KeyMap {
+    bindings: HashMap<Keycode, u8>,
+}
+
+impl KeyMap {
+
+    /// Creates a key map without any bindings.
+    pub fn empty() -> Self {
+        KeyMap { bindings: HashMap::new() }
+    }
+
+    /// Builds a key map from a string of 16 characters, where the character at
+    /// position N is the host key bound to keypad key N.
+    pub fn from_layout(layout: &str) -> Result<Self, String> {
+        let chars: Vec<char> = layout.chars().collect();
+
+        if chars.len() != KEY_COUNT {
+            return Err(format!("key layout must have {} keys, got {}", KEY_COUNT, chars.len()));
+        }
+
+        let mut keymap = KeyMap::empty();
+
+        for (key, c) in chars.iter().enumerate() {
+            let keycode = Keycode::from_name(&c.to_string())
+                .ok_or_else(|| format!("unknown key '{}' in key layout", c))?;
+
+            keymap.bind(keycode, key as u8);
+        }
+
+        Ok(keymap)
+    }
+
+    /// Binds a host key to a keypad key, replacing any previous binding of the host key.
+    pub fn bind(&mut self, keycode: Keycode, key: u8) {
+        self.bindings.insert(keycode, key & 0xF);
+    }
+
+    /// Returns the keypad key bound to the host key if there is one.
+    pub fn get(&self, keycode: Keycode) -> Option<u8> {
+        self.bindings.get(&keycode).copied()
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap::from_layout(DEFAULT_LAYOUT).unwrap()
+    }
+}
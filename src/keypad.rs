@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+
+/// Number of keys on the hexadecimal keypad.
+pub const KEY_COUNT: usize = 16;
+
+/// Holds the state of each key on the 16 key hexadecimal keypad.
+pub struct Keypad {
+    keys: [bool; KEY_COUNT],
+}
+
+impl Keypad {
+
+    pub fn new() -> Self {
+        Keypad { keys: [false; KEY_COUNT] }
+    }
+
+    /// Marks the key as held down, only the lowest nibble of the key is used.
+    pub fn press(&mut self, key: u8) {
+        self.keys[(key & 0xF) as usize] = true;
+    }
+
+    /// Marks the key as released, only the lowest nibble of the key is used.
+    pub fn release(&mut self, key: u8) {
+        self.keys[(key & 0xF) as usize] = false;
+    }
+
+    /// Releases every key, used when the window loses focus.
+    pub fn release_all(&mut self) {
+        self.keys = [false; KEY_COUNT];
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+
+    /// Returns the lowest key currently held down if there is one.
+    pub fn first_pressed(&self) -> Option<u8> {
+        self.keys.iter().position(|&pressed| pressed).map(|key| key as u8)
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Keypad::new()
+    }
+}
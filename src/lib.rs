@@ -0,0 +1,33 @@
+//! CHIP-8, SUPER-CHIP and XO-CHIP interpreter core. The processor, screen
+//! model and tooling have no window or sound dependency, frontends present
+//! the framebuffer through a `Display` and play the beep themselves. The
+//! SDL window is only built with the `sdl` feature.
+
+pub mod screen;
+pub mod processor;
+pub mod collections;
+pub mod font;
+pub mod keypad;
+pub mod scheduler;
+pub mod headless;
+pub mod png;
+pub mod savestate;
+pub mod debugger;
+pub mod instruction;
+pub mod disassembler;
+pub mod assembler;
+pub mod tracer;
+pub mod quirks;
+pub mod framebuffer;
+pub mod display;
+
+/// Size of the standard CHIP-8 screen.
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+/// Size of the SUPER-CHIP high resolution screen.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+/// Default size of a CHIP-8 pixel in window pixels.
+pub const SCALE: usize = 16;
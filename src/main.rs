@@ -1,75 +1,153 @@
-extern crate sdl2;
+mod cli;
+#[cfg(feature = "sdl")]
+mod keymap;
+#[cfg(feature = "sdl")]
+mod audio;
+#[cfg(feature = "sdl")]
+mod window;
+
+use chip_8_rust::{assembler, disassembler, headless, quirks};
+use chip_8_rust::collections::CallStack;
+use chip_8_rust::processor::processor::Processor;
+use chip_8_rust::quirks::Quirks;
+use chip_8_rust::screen::Screen;
+use chip_8_rust::tracer::{TraceFilter, Tracer};
+use cli::{Command, Options, USAGE};
+#[cfg(feature = "sdl")]
+use window::run_window;
+use std::fs;
+use std::path::Path;
+use std::process;
 
-mod screen;
-mod processor;
-mod collections;
+pub fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => fail(&format!("{}\n\n{}", message, USAGE)),
+    };
+
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
 
-use screen::Screen;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use std::fs::File;
-use std::io::Read;
-use std::time::Duration;
-use processor::processor::Processor;
+    if options.command == Command::Asm {
+        assemble(&options.rom, options.output.as_deref()).unwrap_or_else(|message| fail(&message));
+        return;
+    }
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
-const SCALE: usize = 16;
+    let rom = read_rom(&options.rom).unwrap_or_else(|message| fail(&message));
 
-pub fn main() {
-    let sdl_context: sdl2::Sdl = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    if options.command == Command::Disasm {
+        print!("{}", disassembler::disassemble(&rom));
+        return;
+    }
 
-    let window = video_subsystem.window("rust-sdl2 demo", (WIDTH * SCALE) as u32, (HEIGHT * SCALE) as u32)
-        .position_centered()
-        .build()
-        .unwrap();
+    // Quirks only change how the program runs, disassembly ignores them.
+    let quirks = Quirks::preset(&options.quirks).unwrap_or_else(|| {
+        fail(&format!("unknown quirks '{}', expected one of {}", options.quirks, quirks::PRESETS.join(", ")))
+    });
 
-    let mut canvas = window.into_canvas().build().unwrap();
-    let mut screen: Screen = Screen::new(Some(&mut canvas));
+    if options.headless {
+        let mut screen: Screen = Screen::new();
+        let mut processor: Processor = Processor::new(&mut screen);
 
-    screen.setup();
+        configure(&mut processor, rom, &options, quirks);
+        run_headless(&mut processor, &options).unwrap_or_else(|message| fail(&message));
+        return;
+    }
 
-    let mut processor: Processor = Processor::new(&mut screen);
+    run_window(&options, rom, quirks);
+}
 
-    load_file(&mut processor);
+/// Loads the ROM and applies the options that change how the processor runs.
+fn configure(processor: &mut Processor, rom: Vec<u8>, options: &Options, quirks: Quirks) {
+    processor.load(rom).unwrap_or_else(|err| fail(&err.to_string()));
+    processor.quirks = quirks;
+    processor.stack = CallStack::new(options.stack_depth);
+    processor.unknown_policy = options.on_unknown;
+    processor.sys_policy = options.on_sys;
+    processor.tracer = open_tracer(options).unwrap_or_else(|message| fail(&message));
+}
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
+/// Without SDL there is no window, only the other modes are available.
+#[cfg(not(feature = "sdl"))]
+fn run_window(_options: &Options, _rom: Vec<u8>, _quirks: Quirks) {
+    fail("this build has no window support, use --headless, disasm or asm, or rebuild with the sdl feature");
+}
 
-    'running: loop {
+/// Runs the loaded program as fast as possible without a window, stopping
+/// after the number of cycles or frames in the options, then prints the
+/// registers and the framebuffer. When the program fails the state it
+/// stopped in is still printed before the error is returned.
+fn run_headless(processor: &mut Processor, options: &Options) -> Result<(), String> {
+    let frames = headless::frame_limit(options.frames, options.cycles);
 
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit {..} |
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    break 'running
-                },
+    let result = headless::run_frames(processor, options.speed, frames, options.cycles);
 
-                _ => {}
-            }
-        }
+    report_logged_opcodes(processor);
+    print!("{}", processor.register_dump());
 
-        processor.tick();
-        processor.fetch();
-        processor.execute();
+    if !processor.opcode_counters.is_empty() {
+        println!("{}", processor.opcode_counters);
+    }
 
-        if processor.screen.update_screen {
-            processor.screen.draw();
-            processor.screen.update_screen = false;
+    match &options.dump {
+        Some(path) => write_dump(processor, path, options.scale)?,
+        None => {
+            println!();
+            print!("{}", processor.screen.to_ascii());
         }
-        
-        ::std::thread::sleep(Duration::from_millis(1));
     }
 
+    result.map_err(|err| err.to_string())
 }
 
-fn load_file(memory: &mut Processor) {
-    // Read in file
-    let mut file = File::open("roms/test_opcode.ch8").expect("File not found");
+/// Warns about the opcodes the log policy skipped for the first time.
+fn report_logged_opcodes(processor: &mut Processor) {
+    for logged in processor.take_logged_opcodes() {
+        eprintln!("warning: {}", logged);
+    }
+}
+
+/// Writes the framebuffer to a file.
+fn write_dump(processor: &Processor, path: &str, scale: usize) -> Result<(), String> {
+    processor.screen
+        .save_image(path, scale)
+        .map_err(|err| format!("could not write '{}': {}", path, err))
+}
 
-    let mut buffer: Vec<u8> = Vec::new();
+/// Reads the whole ROM file into memory.
+fn read_rom(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("could not read ROM '{}': {}", path, err))
+}
+
+/// Creates the tracer asked for on the command line, if any.
+fn open_tracer(options: &Options) -> Result<Option<Tracer>, String> {
+    let path = match &options.trace {
+        Some(path) => path,
+        None => return Ok(None),
+    };
 
-    file.read_to_end(&mut buffer).expect("Failed to read the file");
+    let mut tracer = Tracer::create(path, options.trace_last)
+        .map_err(|err| format!("could not create trace file '{}': {}", path, err))?;
 
-    memory.load(buffer);
-}
\ No newline at end of file
+    tracer.filter = TraceFilter::parse(options.trace_pc.as_deref(), options.trace_ops.as_deref())?;
+    Ok(Some(tracer))
+}
+
+/// Assembles the source file into a ROM, next to the source unless an output is given.
+fn assemble(source: &str, output: Option<&str>) -> Result<(), String> {
+    let rom = assembler::assemble_file(Path::new(source)).map_err(|err| err.to_string())?;
+    let output = output.map(Path::new).map(Path::to_path_buf)
+        .unwrap_or_else(|| Path::new(source).with_extension("ch8"));
+
+    fs::write(&output, &rom).map_err(|err| format!("could not write ROM '{}': {}", output.display(), err))?;
+    println!("assembled {} bytes to {}", rom.len(), output.display());
+    Ok(())
+}
+
+/// Prints the error message and exits with a non-zero status.
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
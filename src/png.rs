@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+
+/// Every PNG file starts with this signature.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Largest amount of data a single stored deflate block can hold.
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// Writes an indexed color PNG image using the palette, which can hold up
+/// to 256 colors. The closure is called for every pixel and returns its
+/// index into the palette.
+///
+/// The image data is stored without compression, which keeps the encoder
+/// small while still producing files every viewer can open.
+pub fn write_png<W, F>(out: &mut W, width: usize, height: usize, palette: &[(u8, u8, u8)], pixel: F) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(usize, usize) -> usize,
+{
+    // The smallest bit depth that can index every color in the palette.
+    let depth = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+
+    out.write_all(&SIGNATURE)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[depth as u8, 3, 0, 0, 0]); // bit depth, indexed, deflate, filter, no interlace
+    write_chunk(out, b"IHDR", &header)?;
+
+    let colors: Vec<u8> = palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+    write_chunk(out, b"PLTE", &colors)?;
+
+    // Each row starts with the filter type, followed by the pixel indices
+    // packed most significant bits first.
+    let per_byte = 8 / depth;
+    let mask = (1 << depth) - 1;
+    let row_len = width.div_ceil(per_byte);
+    let mut raw = Vec::with_capacity((row_len + 1) * height);
+
+    for y in 0..height {
+        raw.push(0);
+
+        for byte in 0..row_len {
+            let mut packed = 0u8;
+
+            for slot in 0..per_byte {
+                let x = byte * per_byte + slot;
+
+                if x < width {
+                    let shift = 8 - depth * (slot + 1);
+                    packed |= ((pixel(x, y) & mask) << shift) as u8;
+                }
+            }
+
+            raw.push(packed);
+        }
+    }
+
+    write_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Writes a chunk with its length, type, data and checksum.
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    let crc = crc32(kind.iter().chain(data.iter()));
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps the data in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[0x01, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let len = block.len() as u16;
+
+        stream.push(last);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+pub(crate) fn crc32<'a, I: Iterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for &byte in bytes {
+        crc ^= byte as u32;
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests;
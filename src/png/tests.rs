@@ -0,0 +1,45 @@
+use crate::png::{adler32, crc32, write_png};
+
+#[test]
+fn test_checksums() {
+    assert_eq!(crc32(b"IEND".iter()), 0xAE42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+}
+
+#[test]
+fn test_write_png() {
+    let mut out: Vec<u8> = Vec::new();
+
+    // 9x2 image with a single white pixel in the top left corner.
+    write_png(&mut out, 9, 2, &[(0, 0, 0), (255, 255, 255)], |x, y| (x == 0 && y == 0) as usize).unwrap();
+
+    assert_eq!(&out[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    assert_eq!(&out[12..16], b"IHDR");
+    assert_eq!(&out[16..20], &9u32.to_be_bytes());
+    assert_eq!(&out[20..24], &2u32.to_be_bytes());
+    assert_eq!(&out[24..26], &[1, 3]);
+    assert_eq!(&out[out.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+    // The stored rows are a filter byte followed by 2 bytes of packed pixels.
+    let idat = out.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+    assert_eq!(&out[idat + 7..idat + 13], &[0, 0x80, 0x00, 0, 0x00, 0x00]);
+}
+
+#[test]
+fn test_write_png_two_bit_palette() {
+    let mut out: Vec<u8> = Vec::new();
+    let palette = [(0, 0, 0), (255, 255, 255), (255, 102, 0), (102, 34, 0)];
+
+    // 5x1 image cycling through the four colors.
+    write_png(&mut out, 5, 1, &palette, |x, _| x % 4).unwrap();
+
+    assert_eq!(&out[24..26], &[2, 3]);
+
+    let plte = out.windows(4).position(|w| w == b"PLTE").unwrap();
+    assert_eq!(&out[plte - 4..plte], &12u32.to_be_bytes());
+    assert_eq!(&out[plte + 4..plte + 16], &[0, 0, 0, 255, 255, 255, 255, 102, 0, 102, 34, 0]);
+
+    // Four pixels are packed into each byte, two bits each.
+    let idat = out.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+    assert_eq!(&out[idat + 7..idat + 10], &[0, 0b00_01_10_11, 0b00_00_00_00]);
+}
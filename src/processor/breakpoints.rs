@@ -0,0 +1,159 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Kind of memory access a watchpoint fires on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            Access::Read => !write,
+            Access::Write => write,
+            Access::ReadWrite => true,
+        }
+    }
+}
+
+/// Register a watchpoint can be placed on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Register {
+    V(u8),
+    I,
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+        }
+    }
+}
+
+/// Describes what stopped execution. The pc is the address of the
+/// instruction that triggered the watchpoint.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BreakReason {
+    /// The program counter reached a breakpoint address.
+    Pc(usize),
+
+    /// The next instruction matches an opcode breakpoint.
+    Opcode { pc: usize, opcode: u16 },
+
+    MemoryRead { pc: usize, address: usize, value: u8 },
+
+    MemoryWrite { pc: usize, address: usize, old: u8, new: u8 },
+
+    Register { pc: usize, register: Register, old: usize, new: usize },
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BreakReason::Pc(pc) => write!(f, "breakpoint at {:#05X}", pc),
+            BreakReason::Opcode { pc, opcode } => {
+                write!(f, "opcode breakpoint on {:04X} at {:#05X}", opcode, pc)
+            },
+            BreakReason::MemoryRead { pc, address, value } => {
+                write!(f, "read of {:02X} from {:#05X} by instruction at {:#05X}", value, address, pc)
+            },
+            BreakReason::MemoryWrite { pc, address, old, new } => {
+                write!(f, "write to {:#05X} changed {:02X} to {:02X} by instruction at {:#05X}", address, old, new, pc)
+            },
+            BreakReason::Register { pc, register, old, new } => {
+                write!(f, "{} changed {:X} to {:X} by instruction at {:#05X}", register, old, new, pc)
+            },
+        }
+    }
+}
+
+/// Watchpoint on a range of memory addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<usize>,
+    pub access: Access,
+}
+
+/// Breakpoints and watchpoints checked by the processor on every cycle.
+/// Execution stops before the instruction at a breakpoint runs and right
+/// after the instruction that triggered a watchpoint.
+#[derive(Clone, Debug, Default)]
+pub struct Breakpoints {
+    pub pc: Vec<usize>,
+
+    /// Opcode breakpoints as (value, mask) pairs, the next opcode matches
+    /// when opcode & mask == value.
+    pub opcodes: Vec<(u16, u16)>,
+
+    pub memory: Vec<Watchpoint>,
+
+    pub registers: Vec<Register>,
+}
+
+impl Breakpoints {
+
+    pub fn new() -> Self {
+        Breakpoints::default()
+    }
+
+    pub fn add_pc(&mut self, address: usize) {
+        if !self.pc.contains(&address) {
+            self.pc.push(address);
+        }
+    }
+
+    pub fn remove_pc(&mut self, address: usize) {
+        self.pc.retain(|&pc| pc != address);
+    }
+
+    /// Breaks on every opcode where opcode & mask == value & mask, so a mask
+    /// of 0xF000 with a value of 0x2000 breaks on every CALL.
+    pub fn add_opcode(&mut self, value: u16, mask: u16) {
+        let opcode = (value & mask, mask);
+
+        if !self.opcodes.contains(&opcode) {
+            self.opcodes.push(opcode);
+        }
+    }
+
+    pub fn watch_memory(&mut self, range: RangeInclusive<usize>, access: Access) {
+        self.memory.push(Watchpoint { range, access });
+    }
+
+    /// Watches the register, failing for a V register past VF.
+    pub fn watch_register(&mut self, register: Register) -> Result<(), String> {
+        if let Register::V(x) = register {
+            if x > 0xF {
+                return Err(format!("invalid register V{:X}, expected V0 to VF", x));
+            }
+        }
+
+        if !self.registers.contains(&register) {
+            self.registers.push(register);
+        }
+
+        Ok(())
+    }
+
+    /// Removes every breakpoint and watchpoint.
+    pub fn clear(&mut self) {
+        *self = Breakpoints::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pc.is_empty() && self.opcodes.is_empty() && self.memory.is_empty() && self.registers.is_empty()
+    }
+
+    pub(crate) fn hits_opcode(&self, opcode: u16) -> bool {
+        self.opcodes.iter().any(|&(value, mask)| opcode & mask == value)
+    }
+
+    pub(crate) fn hits_memory(&self, address: usize, write: bool) -> bool {
+        self.memory.iter().any(|w| w.access.matches(write) && w.range.contains(&address))
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt;
+
+/// Errors that stop the processor. Apart from RomTooLarge they carry the
+/// address of the instruction that failed and its opcode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmuError {
+
+    /// The program does not fit in memory after the interpreter area.
+    RomTooLarge { size: usize, max: usize },
+
+    /// The program counter left memory. The opcode is the last instruction
+    /// run, which usually is the jump or call that went there.
+    PcOutOfBounds { pc: usize, opcode: u16 },
+
+    /// 00EE ran with no return address on the stack.
+    StackUnderflow { pc: usize, opcode: u16 },
+
+    /// 2NNN ran with the stack already full.
+    StackOverflow { pc: usize, opcode: u16 },
+
+    /// The opcode is not an instruction of any supported interpreter, or a
+    /// machine code call, and the policy for it is to trap.
+    InvalidOpcode { pc: usize, opcode: u16 },
+
+    /// The instruction read or wrote past the end of memory.
+    MemoryOutOfBounds { pc: usize, opcode: u16, address: usize },
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmuError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, the most that fits in memory is {}", size, max)
+            },
+            EmuError::PcOutOfBounds { pc, opcode } => {
+                write!(f, "program counter {:#05X} is outside memory after {:04X}", pc, opcode)
+            },
+            EmuError::StackUnderflow { pc, opcode } => {
+                write!(f, "{:04X} at {:#05X} returned with an empty stack", opcode, pc)
+            },
+            EmuError::StackOverflow { pc, opcode } => {
+                write!(f, "{:04X} at {:#05X} called with a full stack", opcode, pc)
+            },
+            EmuError::InvalidOpcode { pc, opcode } => {
+                write!(f, "invalid opcode {:04X} at {:#05X}", opcode, pc)
+            },
+            EmuError::MemoryOutOfBounds { pc, opcode, address } => {
+                write!(f, "{:04X} at {:#05X} accessed {:#05X} outside memory", opcode, pc, address)
+            },
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
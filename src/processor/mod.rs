@@ -1,5 +1,9 @@
 // Declare the submodules for your project
+#[allow(clippy::module_inception)]
 pub mod processor;
+pub mod breakpoints;
+pub mod error;
+pub mod policy;
 
 #[cfg(test)]
-pub mod tests;
\ No newline at end of file
+pub mod tests;
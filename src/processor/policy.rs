@@ -0,0 +1,76 @@
+use std::fmt;
+
+/// Names accepted by OpcodePolicy::parse.
+pub const POLICIES: [&str; 3] = ["trap", "log", "ignore"];
+
+/// Called for 0NNN with the address and the registers and memory the
+/// machine code routine would have worked on.
+pub type SysHook = Box<dyn FnMut(usize, &mut [u8; 16], &mut [u8])>;
+
+/// What the processor does with an opcode it cannot run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpcodePolicy {
+
+    /// Stop with an InvalidOpcode error.
+    Trap,
+
+    /// Skip it, recording it the first time each opcode is seen so the
+    /// frontend can warn about it.
+    Log,
+
+    /// Skip it without saying anything.
+    Ignore,
+}
+
+impl OpcodePolicy {
+
+    /// Returns the policy with the given name, see POLICIES.
+    pub fn parse(name: &str) -> Option<OpcodePolicy> {
+        match name {
+            "trap" => Some(OpcodePolicy::Trap),
+            "log" => Some(OpcodePolicy::Log),
+            "ignore" => Some(OpcodePolicy::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// Unsupported opcode skipped by the log policy, recorded the first time
+/// each opcode is seen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoggedOpcode {
+    pub pc: usize,
+    pub opcode: u16,
+}
+
+impl fmt::Display for LoggedOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "skipping unsupported opcode {:04X} at {:#05X}", self.opcode, self.pc)
+    }
+}
+
+/// Number of times each policy was applied, along with the 0NNN hook.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpcodeCounters {
+    pub trapped: u64,
+    pub logged: u64,
+    pub ignored: u64,
+    pub hooked: u64,
+}
+
+impl OpcodeCounters {
+
+    /// True when no opcode has been handled by a policy or the hook.
+    pub fn is_empty(&self) -> bool {
+        *self == OpcodeCounters::default()
+    }
+}
+
+impl fmt::Display for OpcodeCounters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "unsupported opcodes: {} trapped, {} logged, {} ignored, {} hooked",
+            self.trapped, self.logged, self.ignored, self.hooked,
+        )
+    }
+}
@@ -1,17 +1,59 @@
 #![allow(dead_code)]
 
-use crate::collections::Stack;
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::{HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+use crate::collections::{CallStack, DEFAULT_STACK_DEPTH};
+use crate::processor::breakpoints::{BreakReason, Breakpoints, Register};
+use crate::processor::error::EmuError;
+use crate::processor::policy::{LoggedOpcode, OpcodeCounters, OpcodePolicy, SysHook};
+use crate::framebuffer::{Framebuffer, PLANES};
+use crate::font::{BigFont, Font, BIG_FONT_SIZE, DEFAULT_BIG_FONT, DEFAULT_FONT, FONT_SIZE};
+use crate::instruction::{decode, Instruction};
+use crate::keypad::Keypad;
+use crate::quirks::Quirks;
+use crate::savestate::{SaveState, SaveStateError};
 use crate::screen::Screen;
+use crate::tracer::{TraceEntry, Tracer};
+
+/// XO-CHIP extends the memory to 64 KiB, programs for the other
+/// interpreters only use the first 4 KiB.
+pub const MEM_SIZE: usize = 0x10000;
+
+/// Size of the XO-CHIP audio pattern buffer, 128 one bit samples.
+pub const PATTERN_SIZE: usize = 16;
+
+/// Pitch register value that plays the audio pattern at 4000 samples a second.
+pub const DEFAULT_PATTERN_PITCH: u8 = 64;
+
+/// Number of samples of an XO-CHIP audio pattern played each second for
+/// the value of the pitch register.
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - DEFAULT_PATTERN_PITCH as f32) / 48.0)
+}
 
-const MEM_SIZE: usize = 4096;
 const REGISTER_COUNT: usize = 16;
 
 const MEM_START: usize = 0x200;
 
+/// Start of the hexadecimal font sprites in the reserved interpreter memory,
+/// each digit sprite is 5 bytes long.
+const FONT_START: usize = 0x50;
+const FONT_CHAR_SIZE: usize = 5;
+
+/// The SUPER-CHIP large font follows the small one, 10 bytes per digit.
+const BIG_FONT_START: usize = FONT_START + FONT_SIZE;
+const BIG_FONT_CHAR_SIZE: usize = 10;
+
+/// Number of RPL user flags saved and restored by FX75 and FX85.
+const RPL_FLAGS: usize = 16;
+
 /// Struct that will not only hold all the information necessary but will
 /// have the implementation to execute instructions based on its state.
 #[allow(non_snake_case)]
-pub struct Processor<'b, 'c> {
+pub struct Processor<'b> {
 
     /// Index register to point at locations in memory.
     pub I: usize,
@@ -30,48 +72,140 @@ pub struct Processor<'b, 'c> {
     /// a rate of 60hz until it reaches 0.
     pub sound_timer: u8,
 
-    /// Stack used to store addresses to call and return from subroutines
-    pub stack: Stack<usize>,
+    /// Stack used to store addresses to call and return from subroutines.
+    /// Replace it with a new CallStack to change the depth.
+    pub stack: CallStack,
 
     /// We have 16 general purpose registers from V0 to VF, so we can represent
     /// each register as an array and use hexadecimal formatting to index each value.
     pub V: [u8; REGISTER_COUNT],
 
-    /// Array used to actually behave like the main memory for a Chip-8 Interpreter.
-    memory: [u8; MEM_SIZE],
+    /// State of each key on the 16 key hexadecimal keypad.
+    pub keypad: Keypad,
+
+    /// Behaves like the main memory for a Chip-8 Interpreter.
+    memory: Vec<u8>,
+
+    /// Screen reference for our actual program, the frontend presents it
+    /// however it likes between frames.
+    pub screen: &'b mut Screen,
+
+    /// Number of instructions executed through cycle_cpu since the processor was created.
+    pub cycles: u64,
+
+    /// Breakpoints checked before every instruction and watchpoints checked after.
+    pub breakpoints: Breakpoints,
+
+    /// Set when a breakpoint or watchpoint fires, until it is taken by the frontend.
+    break_reason: Option<BreakReason>,
+
+    /// Address execution resumes from, its pc and opcode breakpoints are
+    /// skipped once so the instruction stopped at can run.
+    resume_pc: Option<usize>,
+
+    /// Records every instruction run through cycle_cpu when set.
+    pub tracer: Option<Tracer>,
 
-    /// Screen reference for our actual program.
-    /// The lifetimes specified include
-    /// b -> holds the lifetime reference of Screen
-    /// c -> holds the lifetime reference of Canvas inside Screen
-    pub screen: &'b mut Screen<'c>,
+    /// Behaviors that differ between interpreters.
+    pub quirks: Quirks,
 
-    /// Used to track the number of tick cycles to update the timers at a rate of 60hz.
-    cycle_count: u8,
+    /// SUPER-CHIP RPL user flags, named after the HP-48 calculator registers they lived in.
+    pub rpl: [u8; RPL_FLAGS],
+
+    /// Set once the program runs 00FD, no more instructions run after that.
+    pub halted: bool,
+
+    /// XO-CHIP audio pattern loaded by F002, played instead of the plain
+    /// tone while the sound timer runs.
+    pub audio_pattern: Option<[u8; PATTERN_SIZE]>,
+
+    /// XO-CHIP pitch register set by FX3A.
+    pub pitch: u8,
+
+    /// Set after drawing with the display wait quirk, no instructions run
+    /// until the next tick.
+    waiting_for_vblank: bool,
+
+    /// What happens to opcodes that are not an instruction of any interpreter.
+    pub unknown_policy: OpcodePolicy,
+
+    /// What happens to 0NNN machine code calls when there is no hook.
+    pub sys_policy: OpcodePolicy,
+
+    /// Runs in place of the machine code routine called by 0NNN.
+    pub sys_hook: Option<SysHook>,
+
+    /// How many opcodes each policy has handled.
+    pub opcode_counters: OpcodeCounters,
+
+    /// Opcodes already recorded by the log policy.
+    logged_opcodes: HashSet<u16>,
+
+    /// Opcodes recorded by the log policy that the frontend has not taken yet.
+    new_logged_opcodes: Vec<LoggedOpcode>,
 }
 
 #[allow(unused_variables)]
-impl<'b, 'c> Processor<'b, 'c> {
+impl<'b> Processor<'b> {
 
     /// Contructor for our memory struct.
-    pub fn new(screen: &'b mut Screen<'c>) -> Self {
-        Processor {
+    pub fn new(screen: &'b mut Screen) -> Self {
+        let mut processor = Processor {
             I: 0,
             pc: MEM_START,
             opcode: 0,
             delay_timer: 0,
             sound_timer: 0,
-            stack: Stack::new(MEM_SIZE),
+            stack: CallStack::new(DEFAULT_STACK_DEPTH),
             V: [0; REGISTER_COUNT],
-            memory: [0; MEM_SIZE],
+            keypad: Keypad::new(),
+            memory: vec![0; MEM_SIZE],
             screen,
-            cycle_count: 0,
-        }
+            cycles: 0,
+            breakpoints: Breakpoints::new(),
+            break_reason: None,
+            resume_pc: None,
+            tracer: None,
+            quirks: Quirks::default(),
+            rpl: [0; RPL_FLAGS],
+            halted: false,
+            audio_pattern: None,
+            pitch: DEFAULT_PATTERN_PITCH,
+            waiting_for_vblank: false,
+            unknown_policy: OpcodePolicy::Trap,
+            sys_policy: OpcodePolicy::Ignore,
+            sys_hook: None,
+            opcode_counters: OpcodeCounters::default(),
+            logged_opcodes: HashSet::new(),
+            new_logged_opcodes: Vec::new(),
+        };
+
+        processor.load_font(&DEFAULT_FONT);
+        processor.load_big_font(&DEFAULT_BIG_FONT);
+        processor
+    }
+
+    /// Loads a font set into the reserved interpreter memory, replacing
+    /// the glyphs used by FX29.
+    pub fn load_font(&mut self, font: &Font) {
+        self.memory[FONT_START .. FONT_START + FONT_SIZE].copy_from_slice(font);
+    }
+
+    /// Loads a large font set after the small one, used by FX30.
+    pub fn load_big_font(&mut self, font: &BigFont) {
+        self.memory[BIG_FONT_START .. BIG_FONT_START + BIG_FONT_SIZE].copy_from_slice(font);
     }
 
     /// Loads the program into memory.
-    pub fn load(&mut self, program: Vec<u8>) {
+    pub fn load(&mut self, program: Vec<u8>) -> Result<(), EmuError> {
+        let max = MEM_SIZE - MEM_START;
+
+        if program.len() > max {
+            return Err(EmuError::RomTooLarge { size: program.len(), max });
+        }
+
         self.memory[MEM_START .. (MEM_START + program.len())].copy_from_slice(&program[..]);
+        Ok(())
     }
 
     /// Used to peek at the value of a specific memory location.
@@ -79,92 +213,416 @@ impl<'b, 'c> Processor<'b, 'c> {
         self.memory[index]
     }
 
-    /// Handle all system updates like delay timer.
+    /// The whole of memory, for tools that inspect it without running anything.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Takes a snapshot of the processor, memory and framebuffer.
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            pc: self.pc,
+            I: self.I,
+            opcode: self.opcode,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            cycles: self.cycles,
+            V: self.V,
+            rpl: self.rpl,
+            halted: self.halted,
+            waiting_for_vblank: self.waiting_for_vblank,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            stack: self.stack.frames().to_vec(),
+            memory: self.memory.to_vec(),
+            width: self.screen.width,
+            height: self.screen.height,
+            pixels: self.screen.pixels.to_rows(),
+            planes: self.screen.planes,
+        }
+    }
+
+    /// Restores a snapshot taken with save_state. The state is checked before
+    /// anything is changed, so an invalid state leaves the processor untouched.
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), SaveStateError> {
+        if state.memory.len() != MEM_SIZE {
+            return Err(SaveStateError::Invalid("memory size"));
+        }
+
+        let resolutions = [(WIDTH, HEIGHT), (HIRES_WIDTH, HIRES_HEIGHT)];
+        if !resolutions.contains(&(state.width, state.height)) {
+            return Err(SaveStateError::Invalid("screen size"));
+        }
+
+        if state.stack.len() > self.stack.depth() {
+            return Err(SaveStateError::Invalid("stack depth"));
+        }
+
+        if state.pixels.len() != state.height || state.pixels.iter().any(|row| row.len() != state.width) {
+            return Err(SaveStateError::Invalid("framebuffer size"));
+        }
+
+        let max_value = (1 << PLANES) - 1;
+        if state.pixels.iter().flatten().any(|&v| v > max_value) {
+            return Err(SaveStateError::Invalid("pixel value"));
+        }
+
+        if state.planes > max_value {
+            return Err(SaveStateError::Invalid("plane mask"));
+        }
+
+        self.pc = state.pc;
+        self.I = state.I;
+        self.opcode = state.opcode;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.cycles = state.cycles;
+        self.V = state.V;
+        self.rpl = state.rpl;
+        self.halted = state.halted;
+        self.waiting_for_vblank = state.waiting_for_vblank;
+        self.audio_pattern = state.audio_pattern;
+        self.pitch = state.pitch;
+        self.memory.copy_from_slice(&state.memory);
+
+        self.stack.clear();
+        for &address in &state.stack {
+            self.stack.push(address).unwrap();
+        }
+
+        self.screen.resize(state.width, state.height);
+        self.screen.pixels = Framebuffer::from_rows(&state.pixels);
+        self.screen.planes = state.planes;
+
+        Ok(())
+    }
+
+    /// Formats the registers, timers and stack depth for printing.
+    pub fn register_dump(&self) -> String {
+        let mut dump = format!(
+            "pc: {:#05X}  opcode: {:04X}  I: {:#05X}  DT: {}  ST: {}  cycles: {}\n",
+            self.pc, self.opcode, self.I, self.delay_timer, self.sound_timer, self.cycles,
+        );
+
+        for (i, v) in self.V.iter().enumerate() {
+            let separator = if i % 8 == 7 { '\n' } else { ' ' };
+            dump.push_str(&format!("V{:X}: {:02X}{}", i, v, separator));
+        }
+
+        dump
+    }
+
+    /// Handle all system updates like the delay timer. This is called once
+    /// per frame by the scheduler, so the timers are decremented at 60hz.
     pub fn tick(&mut self) {
-        self.cycle_count += 1;
+        self.waiting_for_vblank = false;
 
-        // 1hz is a rate of 16.67ms, so when this time passes
-        // update the various timers.
-        if self.cycle_count > 16 {
-            self.cycle_count = 0;
+        // Update delay timer
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        // Update sound timer.
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+
+    /// True after a draw with the display wait quirk, until the next tick.
+    pub fn waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    /// Wrapper function to call one fetch execute cycle.
+    pub fn cycle_cpu(&mut self) -> Result<(), EmuError> {
+        if self.waiting_for_vblank || self.halted {
+            return Ok(());
+        }
+
+        if self.breakpoints.is_empty() {
+            self.resume_pc = None;
 
-            // Update delay timer
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
+            if self.tracer.is_some() {
+                self.trace();
             }
 
-            // Update sound timer.
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
+            self.run_instruction()?;
+            self.cycles += 1;
+            return Ok(());
+        }
+
+        // A breakpoint stops execution before its instruction runs, the
+        // next cycle then runs it.
+        if self.resume_pc.take() != Some(self.pc) && self.check_breakpoints() {
+            self.resume_pc = Some(self.pc);
+            return Ok(());
+        }
+
+        if self.tracer.is_some() {
+            self.trace();
+        }
+
+        let pc = self.pc;
+        let registers = self.V;
+        let index = self.I;
+
+        self.run_instruction()?;
+        self.cycles += 1;
+
+        self.check_registers(pc, registers, index);
+        Ok(())
+    }
+
+    /// Lets the instruction at the program counter run on the next cycle
+    /// even if it has a breakpoint, used when resuming from a pause.
+    pub fn resume(&mut self) {
+        self.resume_pc = Some(self.pc);
+    }
+
+    /// Fetches and runs the next instruction. When it fails, the traced
+    /// instructions that led up to the failure are written out.
+    fn run_instruction(&mut self) -> Result<(), EmuError> {
+        let result = self.fetch().and_then(|()| self.execute());
+
+        if result.is_err() {
+            if let Some(tracer) = &mut self.tracer {
+                tracer.dump();
             }
         }
+
+        result
+    }
+
+    /// Hands the state before the next instruction runs to the tracer.
+    fn trace(&mut self) {
+        let opcode = match self.pc + 1 < MEM_SIZE {
+            true => (self.memory[self.pc] as u16) << 8 | self.memory[self.pc + 1] as u16,
+            false => 0,
+        };
+
+        let entry = TraceEntry {
+            cycle: self.cycles,
+            pc: self.pc,
+            opcode,
+            I: self.I,
+            V: self.V,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+        };
+
+        if let Some(tracer) = &mut self.tracer {
+            tracer.trace(entry);
+        }
     }
 
-    /// Wrapper function to call one fetch execute cycle.
-    pub fn cycle_cpu(&mut self) {
-        self.fetch();
-        self.execute();
+    /// Returns the opcodes the log policy skipped for the first time since
+    /// the last call, for the frontend to report.
+    pub fn take_logged_opcodes(&mut self) -> Vec<LoggedOpcode> {
+        std::mem::take(&mut self.new_logged_opcodes)
+    }
+
+    /// Returns what stopped execution since the last call, if anything did.
+    pub fn take_break(&mut self) -> Option<BreakReason> {
+        self.break_reason.take()
+    }
+
+    /// Records the reason execution should stop, the first reason in a cycle wins.
+    fn set_break(&mut self, reason: BreakReason) {
+        if self.break_reason.is_none() {
+            self.break_reason = Some(reason);
+        }
+    }
+
+    /// Fires the register watchpoints whose values changed during the last instruction.
+    fn check_registers(&mut self, pc: usize, registers: [u8; REGISTER_COUNT], index: usize) {
+        for i in 0..self.breakpoints.registers.len() {
+            let register = self.breakpoints.registers[i];
+
+            // Registers pushed directly past VF are never hit.
+            let (old, new) = match register {
+                Register::V(x) if x as usize >= REGISTER_COUNT => continue,
+                Register::V(x) => (registers[x as usize] as usize, self.V[x as usize] as usize),
+                Register::I => (index, self.I),
+            };
+
+            if old != new {
+                self.set_break(BreakReason::Register { pc, register, old, new });
+            }
+        }
+    }
+
+    /// Fires the pc and opcode breakpoints on the instruction about to run
+    /// next, returns true if either did.
+    fn check_breakpoints(&mut self) -> bool {
+        if self.breakpoints.pc.contains(&self.pc) {
+            self.set_break(BreakReason::Pc(self.pc));
+            return true;
+        }
+
+        if !self.breakpoints.opcodes.is_empty() && self.pc + 1 < MEM_SIZE {
+            let opcode = (self.memory[self.pc] as u16) << 8 | self.memory[self.pc + 1] as u16;
+
+            if self.breakpoints.hits_opcode(opcode) {
+                self.set_break(BreakReason::Opcode { pc: self.pc, opcode });
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Error for the running instruction accessing an address past the end of memory.
+    fn out_of_bounds(&self, address: usize) -> EmuError {
+        EmuError::MemoryOutOfBounds { pc: self.pc - 2, opcode: self.opcode, address }
+    }
+
+    /// Reads a byte on behalf of an instruction, firing any read watchpoints.
+    fn read_memory(&mut self, address: usize) -> Result<u8, EmuError> {
+        let value = *self.memory.get(address).ok_or_else(|| self.out_of_bounds(address))?;
+
+        if self.breakpoints.hits_memory(address, false) {
+            let pc = self.pc - 2;
+            self.set_break(BreakReason::MemoryRead { pc, address, value });
+        }
+
+        Ok(value)
+    }
+
+    /// Writes a byte on behalf of an instruction, firing any write watchpoints.
+    fn write_memory(&mut self, address: usize, value: u8) -> Result<(), EmuError> {
+        if address >= MEM_SIZE {
+            return Err(self.out_of_bounds(address));
+        }
+
+        let old = self.memory[address];
+        self.memory[address] = value;
+
+        if self.breakpoints.hits_memory(address, true) {
+            let pc = self.pc - 2;
+            self.set_break(BreakReason::MemoryWrite { pc, address, old, new: value });
+        }
+
+        Ok(())
     }
 
     /// Fetch the next two bytes in memory and load them into our opcode.
-    pub fn fetch(&mut self) {
+    pub fn fetch(&mut self) -> Result<(), EmuError> {
+        if self.pc + 1 >= MEM_SIZE {
+            return Err(EmuError::PcOutOfBounds { pc: self.pc, opcode: self.opcode });
+        }
+
         let mut hi: u16 = self.memory[self.pc] as u16;
         hi <<= 8;
 
         let lo: u16 = self.memory[self.pc + 1] as u16;
         self.opcode = hi | lo;
         self.pc += 2;
+        Ok(())
     }
 
     /// Determines the instruction to execute based on the current
     /// value of our opcode variable.
-    pub fn execute(&mut self) {
-
-        // Tuple for each nibble value present in our opcode
-        let nibbles: (u8, u8, u8, u8) = (
-            ((self.opcode & 0xF000) >> 12) as u8,
-            ((self.opcode & 0x0F00) >> 8) as u8,
-            ((self.opcode & 0x00F0) >> 4) as u8,
-            ((self.opcode & 0x000F) >> 0) as u8,
-        );
+    pub fn execute(&mut self) -> Result<(), EmuError> {
+        let instruction = match decode(self.opcode) {
+            Ok(instruction) => instruction,
+            Err(_) => return self.unsupported(self.unknown_policy),
+        };
 
-        // Read the potential input from the instruction based on the defined
-        // Chip-8 conventions.
-        let nnn: usize = (self.opcode & 0x0FFF) as usize;
-        let nn: u8 = (self.opcode & 0x00FF) as u8;
-        let x: usize = nibbles.1 as usize;
-        let y: usize = nibbles.2 as usize;
-        let n: u8 = nibbles.3 as u8;
-
-        match nibbles {
-            (0x0, 0x0, 0xe, 0x0)        => self.clear_screen(),
-            (0x0, 0x0, 0xE, 0xE)        => self.return_from_subroutine(),
-            (0x1, _, _, _)              => self.jump(nnn),
-            (0x2, _, _, _)              => self.call_subroutine(nnn),
-            (0x3, _, _, _)              => self.skip_if_equal(x, nn),
-            (0x4, _, _, _)              => self.skip_if_not_equal(x, nn),
-            (0x5, _, _, _)              => self.skip_if_registers_equal(x, y),
-            (0x9, _, _, _)              => self.skip_if_registers_not_equal(x, y),
-            (0x6, _, _, _)              => self.set_register(x, nn),
-            (0x7, _, _, _)              => self.add_immediate(x, nn),
-            (0xA, _, _, _)              => self.set_index(nnn),
-            (0xD, _, _, _)              => self.display(x, y, n),
-            (0x8, _, _, 0x0)            => self.set_vx_vy(x, y),
-            (0x8, _, _, 0x1)            => self.binary_or(x, y),
-            (0x8, _, _, 0x2)            => self.binary_and(x, y),
-            (0x8, _, _, 0x3)            => self.logical_xor(x, y),
-            (0x8, _, _, 0x4)            => self.add_registers(x, y),
-            (0x8, _, _, 0x5)            => self.subtract_vx_vy(x, y),
-            (0x8, _, _, 0x6)            => self.shift_right(x),
-            (0x8, _, _, 0x7)            => self.subtract_vy_vx(x, y),
-            (0x8, _, _, 0xE)            => self.shift_left(x),
-            (0xF, _, _, 0x7)            => self.set_vx_delay(x),
-            (0xF, _, 0x1, 0x5)          => self.set_delay_vx(x),
-            (0xF, _, 0x1, 0x8)          => self.set_sound_vx(x),
-            _ => {},
+        match instruction {
+            Instruction::Sys(nnn)       => self.machine_code_call(nnn as usize)?,
+            Instruction::Cls            => self.clear_screen(),
+            Instruction::Ret            => self.return_from_subroutine()?,
+            Instruction::Scd(n)         => self.screen.scroll_down(n as usize),
+            Instruction::Scu(n)         => self.screen.scroll_up(n as usize),
+            Instruction::Scr            => self.screen.scroll_right(4),
+            Instruction::Scl            => self.screen.scroll_left(4),
+            Instruction::Exit           => self.halted = true,
+            Instruction::Low            => self.screen.set_hires(false),
+            Instruction::High           => self.screen.set_hires(true),
+            Instruction::Jp(nnn)        => self.jump(nnn as usize),
+            Instruction::Call(nnn)      => self.call_subroutine(nnn as usize)?,
+            Instruction::SeByte(x, nn)  => self.skip_if_equal(x as usize, nn),
+            Instruction::SneByte(x, nn) => self.skip_if_not_equal(x as usize, nn),
+            Instruction::SeReg(x, y)    => self.skip_if_registers_equal(x as usize, y as usize),
+            Instruction::SneReg(x, y)   => self.skip_if_registers_not_equal(x as usize, y as usize),
+            Instruction::LdIRange(x, y) => self.store_register_range(x as usize, y as usize)?,
+            Instruction::LdRangeI(x, y) => self.load_register_range(x as usize, y as usize)?,
+            Instruction::LdByte(x, nn)  => self.set_register(x as usize, nn),
+            Instruction::AddByte(x, nn) => self.add_immediate(x as usize, nn),
+            Instruction::LdI(nnn)       => self.set_index(nnn as usize),
+            Instruction::LdILong        => self.set_index_long()?,
+            Instruction::Plane(n)       => self.screen.planes = (n & 0x3) as usize,
+            Instruction::Audio          => self.load_audio_pattern()?,
+            Instruction::Pitch(x)       => self.pitch = self.V[x as usize],
+            Instruction::JpV0(nnn)      => self.jump_with_offset(nnn as usize),
+            Instruction::Rnd(x, nn)     => self.random(x as usize, nn),
+            Instruction::Drw(x, y, n)   => self.display(x as usize, y as usize, n)?,
+            Instruction::Skp(x)         => self.skip_if_key(x as usize),
+            Instruction::Sknp(x)        => self.skip_if_not_key(x as usize),
+            Instruction::LdReg(x, y)    => self.set_vx_vy(x as usize, y as usize),
+            Instruction::Or(x, y)       => self.binary_or(x as usize, y as usize),
+            Instruction::And(x, y)      => self.binary_and(x as usize, y as usize),
+            Instruction::Xor(x, y)      => self.logical_xor(x as usize, y as usize),
+            Instruction::AddReg(x, y)   => self.add_registers(x as usize, y as usize),
+            Instruction::Sub(x, y)      => self.subtract_vx_vy(x as usize, y as usize),
+            Instruction::Shr(x, y)      => self.shift_right(x as usize, y as usize),
+            Instruction::Subn(x, y)     => self.subtract_vy_vx(x as usize, y as usize),
+            Instruction::Shl(x, y)      => self.shift_left(x as usize, y as usize),
+            Instruction::LdVxDt(x)      => self.set_vx_delay(x as usize),
+            Instruction::LdDtVx(x)      => self.set_delay_vx(x as usize),
+            Instruction::LdStVx(x)      => self.set_sound_vx(x as usize),
+            Instruction::LdVxK(x)       => self.wait_for_key(x as usize),
+            Instruction::AddI(x)        => self.add_to_index(x as usize),
+            Instruction::LdF(x)         => self.set_index_font(x as usize),
+            Instruction::LdHf(x)        => self.set_index_big_font(x as usize),
+            Instruction::LdB(x)         => self.store_bcd(x as usize)?,
+            Instruction::LdIVx(x)       => self.store_registers(x as usize)?,
+            Instruction::LdVxI(x)       => self.load_registers(x as usize)?,
+            Instruction::LdRVx(x)       => self.store_flags(x as usize),
+            Instruction::LdVxR(x)       => self.load_flags(x as usize),
         }
 
+        Ok(())
+    }
+
+    /// Applies the policy to the opcode that was just fetched.
+    fn unsupported(&mut self, policy: OpcodePolicy) -> Result<(), EmuError> {
+        let pc = self.pc - 2;
+
+        match policy {
+            OpcodePolicy::Trap => {
+                self.opcode_counters.trapped += 1;
+                Err(EmuError::InvalidOpcode { pc, opcode: self.opcode })
+            },
+            OpcodePolicy::Log => {
+                self.opcode_counters.logged += 1;
 
+                if self.logged_opcodes.insert(self.opcode) {
+                    self.new_logged_opcodes.push(LoggedOpcode { pc, opcode: self.opcode });
+                }
+
+                Ok(())
+            },
+            OpcodePolicy::Ignore => {
+                self.opcode_counters.ignored += 1;
+                Ok(())
+            },
+        }
+    }
+
+    /// OPCODE - 0x0NNN
+    ///
+    /// Called a machine code routine at NNN on the original interpreters. The
+    /// hook runs in its place when one is set, otherwise the sys policy applies.
+    fn machine_code_call(&mut self, nnn: usize) -> Result<(), EmuError> {
+        match &mut self.sys_hook {
+            Some(hook) => {
+                hook(nnn, &mut self.V, &mut self.memory);
+                self.opcode_counters.hooked += 1;
+                Ok(())
+            },
+            None => self.unsupported(self.sys_policy),
+        }
     }
 
     /// OPCODE - 0x00E0
@@ -179,8 +637,12 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// 
     /// This will allow us to return from a subroutine by retrieving the last 
     /// address from the stack andsetting it to the program counter.
-    fn return_from_subroutine(&mut self) {
-        self.pc = self.stack.pop().unwrap();
+    fn return_from_subroutine(&mut self) -> Result<(), EmuError> {
+        self.pc = self.stack
+            .pop()
+            .map_err(|_| EmuError::StackUnderflow { pc: self.pc - 2, opcode: self.opcode })?;
+
+        Ok(())
     }
 
     /// OPCODE - 0x1NNN
@@ -196,9 +658,21 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// Sets the program counter to the parameter passed in the method.  Before
     /// doing so, we need to preserve the current value of the program counter
     /// by pushing it onto the stack.
-    fn call_subroutine(&mut self, nnn: usize) {
-        self.stack.push(self.pc);
+    fn call_subroutine(&mut self, nnn: usize) -> Result<(), EmuError> {
+        self.stack
+            .push(self.pc)
+            .map_err(|_| EmuError::StackOverflow { pc: self.pc - 2, opcode: self.opcode })?;
+
         self.pc = nnn;
+        Ok(())
+    }
+
+    /// Moves the program counter past the next instruction, which is 4 bytes
+    /// long for the XO-CHIP long index load.
+    fn skip(&mut self) {
+        let long = self.memory.get(self.pc .. self.pc + 2) == Some(&[0xF0, 0x00][..]);
+
+        self.pc += if long { 4 } else { 2 };
     }
 
     /// OPCODE - 0x3XNN
@@ -207,7 +681,7 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// by two, skipping the next instruction, if the value is equal to NN.
     fn skip_if_equal(&mut self, x: usize, nn: u8) {
         if self.V[x] == nn {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -217,7 +691,7 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// by two, skipping the next instruction, if the value is NOT equal to NN.
     fn skip_if_not_equal(&mut self, x: usize, nn: u8) {
         if self.V[x] != nn {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -227,18 +701,42 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// counter by two, skipping the next instruction, if the register values are equal.
     fn skip_if_registers_equal(&mut self, x: usize, y: usize) {
         if self.V[x] == self.V[y] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
-    /// OPCODE - 0xXY0
+    /// OPCODE - 0x9XY0
     ///
     /// Will look at the values in register V[x] and V[y] and will increment the program
-    /// counter by two, skipping the next instruction, if the register values are equal.
+    /// counter by two, skipping the next instruction, if the register values are NOT equal.
     fn skip_if_registers_not_equal(&mut self, x: usize, y: usize) {
         if self.V[x] != self.V[y] {
-            self.pc += 2;
+            self.skip();
+        }
+    }
+
+    /// OPCODE - 0x5XY2
+    ///
+    /// Stores the registers V[x] through V[y] inclusive into memory starting at I,
+    /// in reverse order when x is greater than y. I is left unchanged.
+    fn store_register_range(&mut self, x: usize, y: usize) -> Result<(), EmuError> {
+        for (offset, register) in register_range(x, y).enumerate() {
+            self.write_memory(self.I + offset, self.V[register])?;
+        }
+
+        Ok(())
+    }
+
+    /// OPCODE - 0x5XY3
+    ///
+    /// Loads the registers V[x] through V[y] inclusive from memory starting at I,
+    /// in reverse order when x is greater than y. I is left unchanged.
+    fn load_register_range(&mut self, x: usize, y: usize) -> Result<(), EmuError> {
+        for (offset, register) in register_range(x, y).enumerate() {
+            self.V[register] = self.read_memory(self.I + offset)?;
         }
+
+        Ok(())
     }
 
     /// OPCODE - 0x6XNN
@@ -263,6 +761,33 @@ impl<'b, 'c> Processor<'b, 'c> {
         self.I = nnn;
     }
 
+    /// OPCODE - 0xF000 NNNN
+    ///
+    /// Sets the index register I to the 16 bit address in the word following
+    /// the opcode, then moves the program counter past it.
+    fn set_index_long(&mut self) -> Result<(), EmuError> {
+        let hi = self.read_memory(self.pc)? as usize;
+        let lo = self.read_memory(self.pc + 1)? as usize;
+
+        self.I = hi << 8 | lo;
+        self.pc += 2;
+        Ok(())
+    }
+
+    /// OPCODE - 0xF002
+    ///
+    /// Loads the 16 byte audio pattern starting at I.
+    fn load_audio_pattern(&mut self) -> Result<(), EmuError> {
+        let mut pattern = [0; PATTERN_SIZE];
+
+        for (i, sample) in pattern.iter_mut().enumerate() {
+            *sample = self.read_memory(self.I + i)?;
+        }
+
+        self.audio_pattern = Some(pattern);
+        Ok(())
+    }
+
     /// OPCODE - 0x8XY0
     /// 
     /// Sets the value of V[x] to V[y]
@@ -274,21 +799,31 @@ impl<'b, 'c> Processor<'b, 'c> {
     /// 
     /// V[x] is set to the OR of V[x] and V[y]
     fn binary_or(&mut self, x: usize, y: usize) {
-        self.V[x] |= self.V[y]
+        self.V[x] |= self.V[y];
+        self.reset_flag();
     }
 
     /// OPCODE - 0x8XY2
     /// 
     /// V[x] is set to the AND of V[x] and V[y]
     fn binary_and(&mut self, x: usize, y: usize) {
-        self.V[x] &= self.V[y]
+        self.V[x] &= self.V[y];
+        self.reset_flag();
     }
 
     /// OPCODE - 0x8XY3
     /// 
     /// V[x] is set to the XOR of V[x] and V[y]
     fn logical_xor(&mut self, x: usize, y: usize) {
-        self.V[x] ^= self.V[y]
+        self.V[x] ^= self.V[y];
+        self.reset_flag();
+    }
+
+    /// The COSMAC VIP left VF zeroed after the logic operations.
+    fn reset_flag(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.V[0xF] = 0;
+        }
     }
 
     /// OPCODE - 0x8XY4
@@ -309,58 +844,56 @@ impl<'b, 'c> Processor<'b, 'c> {
 
     /// OPCODE - 0x8XY5
     /// 
-    /// This will technically set the value of V[x] to V[x] - V[y]
-    /// but we will conduct it the other way around and set the underflow
-    /// carry flag if V[y] > V[x].
+    /// Sets the value of V[x] to V[x] - V[y]. The carry flag is set to 1
+    /// when no borrow occurs, meaning V[x] >= V[y].
     fn subtract_vx_vy(&mut self, x: usize, y: usize) {
-        if self.V[x] > self.V[y] {
-            self.V[0xF] = 1;
-        } else {
-            self.V[0xF] = 0;
-        }
+        let borrow = if self.V[x] >= self.V[y] { 1 } else { 0 };
 
         self.V[x] = self.V[x].wrapping_sub(self.V[y]);
+        self.V[0xF] = borrow;
     }
 
-    /// OPCODE - 0x0x8X06
+    /// OPCODE - 0x8XY6
     /// 
-    /// Sets V[x] one to the right, preserves the lsb before shifting.
-    fn shift_right(&mut self, x: usize) {
-         self.V[0xF]= self.V[x] & 0x1;
-        self.V[x] >>= 1;
+    /// Shifts V[x] one to the right, or V[y] into V[x] with the shift quirk,
+    /// and stores the bit shifted out in VF.
+    fn shift_right(&mut self, x: usize, y: usize) {
+        let value = if self.quirks.shift_uses_vy { self.V[y] } else { self.V[x] };
+
+        self.V[x] = value >> 1;
+        self.V[0xF] = value & 0x1;
     }
 
-    /// OPCODE - 0x0x8X0E
+    /// OPCODE - 0x8XYE
     /// 
-    /// Shifts V[x] one to the left, preserves the msb before shifting.
-    fn shift_left(&mut self, x: usize) {
-        self.V[0xF] = (self.V[x] & 0b10000000) >> 7;
-        self.V[x] <<= 1;
+    /// Shifts V[x] one to the left, or V[y] into V[x] with the shift quirk,
+    /// and stores the bit shifted out in VF.
+    fn shift_left(&mut self, x: usize, y: usize) {
+        let value = if self.quirks.shift_uses_vy { self.V[y] } else { self.V[x] };
+
+        self.V[x] = value << 1;
+        self.V[0xF] = (value & 0b10000000) >> 7;
     }
 
-    /// OPCODE - 0x8XY5
+    /// OPCODE - 0x8XY7
     /// 
-    /// This will technically set the value of V[x] to V[x] - V[y]
-    /// but we will conduct it the other way around and set the underflow
-    /// carry flag if V[y] > V[x].
+    /// Sets the value of V[x] to V[y] - V[x]. The carry flag is set to 1
+    /// when no borrow occurs, meaning V[y] >= V[x].
     fn subtract_vy_vx(&mut self, x: usize, y: usize) {
-        if self.V[y] > self.V[x] {
-            self.V[0xF] = 1;
-        } else {
-            self.V[0xF] = 0;
-        }
+        let borrow = if self.V[y] >= self.V[x] { 1 } else { 0 };
 
-        self.V[y] = self.V[y].wrapping_sub(self.V[x]);
+        self.V[x] = self.V[y].wrapping_sub(self.V[x]);
+        self.V[0xF] = borrow;
     }
 
-    /// OPCODE - 0xFX15
+    /// OPCODE - 0xFX07
     /// 
     /// Sets the value of V[x] to equal the current value of the delay timer.
     fn set_vx_delay(&mut self, x: usize) {
         self.V[x] = self.delay_timer; 
     }
 
-    /// OPCODE - 0xFX07
+    /// OPCODE - 0xFX15
     /// 
     /// Sets the value of the delay timer to the value of V[x].
     fn set_delay_vx(&mut self, x: usize) {
@@ -374,35 +907,215 @@ impl<'b, 'c> Processor<'b, 'c> {
         self.sound_timer = self.V[x];
     }
 
+    /// OPCODE - 0xBNNN
+    ///
+    /// Jumps to the address NNN plus the value of register V[0]. With the
+    /// jump quirk the offset comes from V[x] instead, X being the top digit of NNN.
+    fn jump_with_offset(&mut self, nnn: usize) {
+        let x = if self.quirks.jump_uses_vx { nnn >> 8 } else { 0x0 };
+
+        self.pc = nnn + self.V[x] as usize;
+    }
+
+    /// OPCODE - 0xCXNN
+    ///
+    /// Generates a random number, binary ANDs it with NN and stores the result in V[x].
+    fn random(&mut self, x: usize, nn: u8) {
+        self.V[x] = rand::thread_rng().gen::<u8>() & nn;
+    }
+
+    /// OPCODE - 0xEX9E
+    ///
+    /// Skips the next instruction if the key corresponding to the value of V[x] is pressed.
+    fn skip_if_key(&mut self, x: usize) {
+        if self.keypad.is_pressed(self.V[x]) {
+            self.skip();
+        }
+    }
+
+    /// OPCODE - 0xEXA1
+    ///
+    /// Skips the next instruction if the key corresponding to the value of V[x] is NOT pressed.
+    fn skip_if_not_key(&mut self, x: usize) {
+        if !self.keypad.is_pressed(self.V[x]) {
+            self.skip();
+        }
+    }
+
+    /// OPCODE - 0xFX0A
+    ///
+    /// Blocks until a key is pressed and stores it in V[x]. We block by moving the
+    /// program counter back so this instruction is fetched again on the next cycle,
+    /// which keeps the timers running while we wait.
+    fn wait_for_key(&mut self, x: usize) {
+        match self.keypad.first_pressed() {
+            Some(key) => self.V[x] = key,
+            None => self.pc -= 2,
+        }
+    }
+
+    /// OPCODE - 0xFX1E
+    ///
+    /// Adds the value of V[x] to the index register I.
+    fn add_to_index(&mut self, x: usize) {
+        self.I += self.V[x] as usize;
+    }
+
+    /// OPCODE - 0xFX29
+    ///
+    /// Sets the index register I to the address of the font sprite for the
+    /// hexadecimal digit in the lowest nibble of V[x].
+    fn set_index_font(&mut self, x: usize) {
+        self.I = FONT_START + (self.V[x] & 0xF) as usize * FONT_CHAR_SIZE;
+    }
+
+    /// OPCODE - 0xFX30
+    ///
+    /// Sets the index register I to the address of the large font sprite for the
+    /// hexadecimal digit in the lowest nibble of V[x].
+    fn set_index_big_font(&mut self, x: usize) {
+        self.I = BIG_FONT_START + (self.V[x] & 0xF) as usize * BIG_FONT_CHAR_SIZE;
+    }
+
+    /// OPCODE - 0xFX33
+    ///
+    /// Stores the binary-coded decimal representation of V[x] at I, I + 1 and I + 2,
+    /// with the hundreds digit at I.
+    fn store_bcd(&mut self, x: usize) -> Result<(), EmuError> {
+        let value = self.V[x];
+
+        self.write_memory(self.I, value / 100)?;
+        self.write_memory(self.I + 1, (value / 10) % 10)?;
+        self.write_memory(self.I + 2, value % 10)
+    }
+
+    /// OPCODE - 0xFX55
+    ///
+    /// Stores the registers V[0] through V[x] inclusive into memory starting at I.
+    /// The index register itself is left unchanged unless the load/store quirk is set.
+    fn store_registers(&mut self, x: usize) -> Result<(), EmuError> {
+        for i in 0..= x {
+            self.write_memory(self.I + i, self.V[i])?;
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.I += x + 1;
+        }
+
+        Ok(())
+    }
+
+    /// OPCODE - 0xFX65
+    ///
+    /// Loads the registers V[0] through V[x] inclusive from memory starting at I.
+    /// The index register itself is left unchanged unless the load/store quirk is set.
+    fn load_registers(&mut self, x: usize) -> Result<(), EmuError> {
+        for i in 0..= x {
+            self.V[i] = self.read_memory(self.I + i)?;
+        }
+
+        if self.quirks.load_store_increments_i {
+            self.I += x + 1;
+        }
+
+        Ok(())
+    }
+
+    /// OPCODE - 0xFX75
+    ///
+    /// Stores the registers V[0] through V[x] inclusive in the RPL user flags.
+    fn store_flags(&mut self, x: usize) {
+        self.rpl[..= x].copy_from_slice(&self.V[..= x]);
+    }
+
+    /// OPCODE - 0xFX85
+    ///
+    /// Loads the registers V[0] through V[x] inclusive from the RPL user flags.
+    fn load_flags(&mut self, x: usize) {
+        self.V[..= x].copy_from_slice(&self.rpl[..= x]);
+    }
+
     /// OPCODE - 0xDXYN
     /// 
-    /// This is the function for displaying Chip-8 graphics.
-    fn display(&mut self, x: usize, y: usize, n: u8) {
-        let vx = self.V[x] % 64;
-        let vy = self.V[y] % 32;
+    /// This is the function for displaying Chip-8 graphics. The starting position
+    /// always wraps around the screen, the parts of the sprite past the edge are
+    /// clipped or wrapped depending on the quirks. DXY0 draws a 16x16 SUPER-CHIP
+    /// sprite made of two bytes per row.
+    ///
+    /// With XO-CHIP a sprite is drawn to each selected bitplane in turn, the data
+    /// for the second plane following the data for the first.
+    fn display(&mut self, x: usize, y: usize, n: u8) -> Result<(), EmuError> {
+        let width = self.screen.width;
+        let height = self.screen.height;
+
+        let vx = self.V[x] as usize % width;
+        let vy = self.V[y] as usize % height;
 
         self.V[0xF] = 0;
 
-        for y_val in 0..n {
-            let sprite_data = self.memory[self.I + (y_val as usize)];
+        let (sprite_width, rows) = if n == 0 { (16, 16) } else { (8, n as usize) };
+        let sprite_size = rows * sprite_width / 8;
+
+        let selected = self.screen.planes;
+        let planes = [1, 2].into_iter().filter(|plane| selected & plane != 0);
 
-            // Sprite data contains an 8 bit value for each of the 8 pixels in the row
+        for (index, plane) in planes.enumerate() {
+            let start = self.I + index * sprite_size;
+
+            self.draw_sprite(start, vx, vy, sprite_width, rows, plane)?;
+        }
+
+        self.waiting_for_vblank = self.quirks.display_wait;
+        Ok(())
+    }
+
+    /// Draws one sprite into a bitplane, setting VF when a lit pixel is turned off.
+    fn draw_sprite(&mut self, start: usize, vx: usize, vy: usize, sprite_width: usize, rows: usize, plane: usize) -> Result<(), EmuError> {
+        let width = self.screen.width;
+        let height = self.screen.height;
+
+        for y_val in 0..rows {
+            let sprite_data: u16 = if sprite_width == 16 {
+                (self.read_memory(start + y_val * 2)? as u16) << 8 | self.read_memory(start + y_val * 2 + 1)? as u16
+            } else {
+                (self.read_memory(start + y_val)? as u16) << 8
+            };
+
+            // Sprite data contains a bit for each of the pixels in the row
             // (x, yVal), so we need evaluate each potential bit and set the pixels accordingly
-            for x_val in 0u8..8 {
-                if sprite_data & (0x80 >> x_val) != 0 {
+            for x_val in 0..sprite_width {
+                if sprite_data & (0x8000 >> x_val) != 0 {
+
+                    let mut x_coord = vx + x_val;
+                    let mut y_coord = vy + y_val;
 
-                    let x_coord = vx + x_val;
-                    let y_coord = vy + y_val;
+                    if x_coord >= width || y_coord >= height {
+                        if !self.quirks.wrap_sprites {
+                            continue;
+                        }
 
-                    if self.screen.get_pixel(x_coord as usize, y_coord as usize) == 1{
+                        x_coord %= width;
+                        y_coord %= height;
+                    }
+
+                    if self.screen.get_pixel(x_coord, y_coord) & plane != 0 {
                         self.V[0xF] = 1;
                     }
 
-                    self.screen.update_pixel(x_coord as usize, y_coord as usize);
+                    self.screen.toggle_pixel(x_coord, y_coord, plane);
                 }
             }
         }
-        
-        self.screen.update_screen = true;
+
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Register indexes from x to y inclusive, counting down when x is greater than y.
+fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..= y)
+    } else {
+        Box::new((y..= x).rev())
+    }
+}
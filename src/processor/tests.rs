@@ -1,15 +1,23 @@
 use crate::WIDTH;
 use crate::HEIGHT;
 
+use crate::assembler::assemble;
+use crate::collections::CallStack;
+use crate::font::DREAM_6800_FONT;
+use crate::processor::breakpoints::{Access, BreakReason, Register};
+use crate::processor::error::EmuError;
+use crate::processor::policy::{LoggedOpcode, OpcodeCounters, OpcodePolicy};
+use crate::quirks::Quirks;
+use crate::savestate::{SaveState, SaveStateError, VERSION};
 use crate::screen::Screen;
-use crate::processor::processor::Processor;
+use crate::processor::processor::{Processor, MEM_SIZE};
 
 #[test]
 fn test_load() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0x1, 0x2, 0x3]);
+    processor.load(vec![0x1, 0x2, 0x3]).unwrap();
 
     assert_eq!(processor.read_byte(0x200), 0x1);
     assert_eq!(processor.read_byte(0x201), 0x2);
@@ -19,24 +27,24 @@ fn test_load() {
 
 #[test]
 fn test_fetch() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0xFF, 0x01, 0x01, 0x01, 0xF0, 0xAF]);
+    processor.load(vec![0xFF, 0x01, 0x01, 0x01, 0xF0, 0xAF]).unwrap();
 
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0xFF01);
 
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0x0101);
 
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0xF0AF);
 }
 
 #[test]
 fn test_clear() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
 
     for x in 0..WIDTH {
         for y in 0..HEIGHT {
@@ -46,10 +54,10 @@ fn test_clear() {
 
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0x00, 0xe0]);
+    processor.load(vec![0x00, 0xe0]).unwrap();
 
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     for x in 0..WIDTH {
         for y in 0..HEIGHT {
@@ -60,28 +68,28 @@ fn test_clear() {
 
 #[test]
 fn test_jump() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0x1A, 0xBC]);
+    processor.load(vec![0x1A, 0xBC]).unwrap();
 
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     assert_eq!(processor.pc, 0xABC);
 }
 
 #[test]
 fn test_subroutine() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0x00, 0x00, 0x22, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x00, 0xEE]);
+    processor.load(vec![0x00, 0x00, 0x22, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x00, 0xEE]).unwrap();
 
     // 4 CPU cycles
     for _ in 0..4 {
-        processor.fetch();
-        processor.execute();
+        processor.fetch().unwrap();
+        processor.execute().unwrap();
     }
 
     assert_eq!(processor.pc, 0x204);
@@ -89,15 +97,15 @@ fn test_subroutine() {
 
 #[test]
 fn test_set_register() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
-    processor.load(vec![0x63, 0xAB, 0x60, 0xCD, 0x6F, 0xEF]);
+    processor.load(vec![0x63, 0xAB, 0x60, 0xCD, 0x6F, 0xEF]).unwrap();
     
     // 3 cpu cycles.
     for _ in 0..3 {
-        processor.fetch();
-        processor.execute();
+        processor.fetch().unwrap();
+        processor.execute().unwrap();
     }
 
     assert_eq!(processor.V[0x3], 0xAB);
@@ -107,7 +115,7 @@ fn test_set_register() {
 
 #[test]
 fn test_skip_if_equal() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
     // 63FF -> Set register 3 to 0xFF
@@ -115,35 +123,35 @@ fn test_skip_if_equal() {
     // CDEF -> Bogus opcode, should be skipped since V[x] = 0xFF
     // 3302 -> Compare register 3 to 0x02
     // ABCD -> Bogus opcode, but will be the value loaded since V[x] = 0x02
-    processor.load(vec![0x63, 0xFF, 0x33, 0xFF, 0xCD, 0xEF, 0x33, 0x02, 0xAB, 0xCD, 0x98, 0x76]);
+    processor.load(vec![0x63, 0xFF, 0x33, 0xFF, 0xCD, 0xEF, 0x33, 0x02, 0xAB, 0xCD, 0x98, 0x76]).unwrap();
 
     // 63FF -> Set register 3 to 0xFF
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 33FF -> Compare register 3 to 0xFF
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // Retrieve the next opcode, the program counter should be incremented
     // since V[x] = 0xFF
-    processor.fetch();
+    processor.fetch().unwrap();
 
     assert_eq!(processor.opcode, 0x3302);
 
     // 4302 -> Compare register 3 to 0x02
-    processor.execute();
+    processor.execute().unwrap();
 
     // Retrieve the next opcode, since V[x] != 0x02 we the next fetch should retrieve
     // 0xABCD as it is the next opcode in memory.
-    processor.fetch();
+    processor.fetch().unwrap();
 
     assert_eq!(processor.opcode, 0xABCD);  
 }
 
 #[test]
 fn test_skip_if_not_equal() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
     // 6302 -> Set register 3 to 0x02
@@ -151,33 +159,33 @@ fn test_skip_if_not_equal() {
     // CDEF -> Bogus opcode, should be skipped since V[x] != 0xFF
     // 4302 -> Compare register 3 to 0x02
     // ABCD -> Bogus opcode, but will be the value loaded since V[x] = 0x02
-    processor.load(vec![0x63, 0x02, 0x43, 0xFF, 0xCD, 0xEF, 0x43, 0x02, 0xAB, 0xCD, 0x98, 0x76]);
+    processor.load(vec![0x63, 0x02, 0x43, 0xFF, 0xCD, 0xEF, 0x43, 0x02, 0xAB, 0xCD, 0x98, 0x76]).unwrap();
 
     // 6302 -> Set register 3 to 0x02
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 33FF -> Compare register 3 to 0xFF
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // Retrieve the next opcode
-    processor.fetch();
+    processor.fetch().unwrap();
 
     assert_eq!(processor.opcode, 0x4302);
 
     // 4302 -> Compare register 3 to 0x02
-    processor.execute();
+    processor.execute().unwrap();
 
     // Retrieve the next opcode
-    processor.fetch();
+    processor.fetch().unwrap();
 
     assert_eq!(processor.opcode, 0xABCD);
 }
 
 #[test]
 fn test_skip_if_registers_equal() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
     // 63AA -> Set register V[3] to 0xAA
@@ -187,38 +195,38 @@ fn test_skip_if_registers_equal() {
     // CDEF -> Bogus opcode, should be skipped since V[3] == V[4]
     // 5350 -> Compare V[3] == V[5]. These values are not equal and so do not skip
     // ABCD -> Bogus opcode, but should be read on next fetch.
-    processor.load(vec![0x63, 0xAA, 0x64, 0xAA, 0x65, 0xBB, 0x53, 0x40, 0xCD, 0xEF, 0x53, 0x50, 0xAB, 0xCD, 0x12, 0x34]);
+    processor.load(vec![0x63, 0xAA, 0x64, 0xAA, 0x65, 0xBB, 0x53, 0x40, 0xCD, 0xEF, 0x53, 0x50, 0xAB, 0xCD, 0x12, 0x34]).unwrap();
 
     // 63AA -> Set register V[3] to 0xAA
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 64AA -> Set register V[4] to 0xAA
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 65BB -> Set register V[5] to 0xBB
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 5340 -> Compare and skip next instruction if V[3] == V[4]
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
     
     // CDEF -> Bogus opcode, should be skipped since V[3] == V[4]
     // 5350 -> Compare V[3] == V[5]. These values are not equal and so do not skip
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0x5350);
-    processor.execute();
+    processor.execute().unwrap();
 
     // ABCD -> Bogus opcode, but should be read on next fetch.   
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0xABCD); 
 }
 
 #[test]
 fn test_skip_if_registers_not_equal() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
     // 63AA -> Set register V[3] to 0xAA
@@ -228,39 +236,39 @@ fn test_skip_if_registers_not_equal() {
     // CDEF -> Bogus opcode, should be skipped since V[3] != V[4]
     // 9350 -> Compare V[3] == V[5]. These values are equal and so do not skip
     // ABCD -> Bogus opcode, but should be read on next fetch.
-    processor.load(vec![0x63, 0xAA, 0x64, 0xBB, 0x65, 0xAA, 0x93, 0x40, 0xCD, 0xEF, 0x93, 0x50, 0xAB, 0xCD, 0x12, 0x34]);
+    processor.load(vec![0x63, 0xAA, 0x64, 0xBB, 0x65, 0xAA, 0x93, 0x40, 0xCD, 0xEF, 0x93, 0x50, 0xAB, 0xCD, 0x12, 0x34]).unwrap();
 
     // 63AA -> Set register V[3] to 0xAA
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 64AA -> Set register V[4] to 0xBB
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 65BB -> Set register V[5] to 0xAA
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
 
     // 9340 -> Compare and skip next instruction since V[3] != V[4]
-    processor.fetch();
-    processor.execute();
+    processor.fetch().unwrap();
+    processor.execute().unwrap();
     
     // CDEF -> Bogus opcode, should be skipped since V[3] != V[4]
     // 5350 -> Compare V[3] == V[5]. These values are equal and so do not skip
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0x9350);
 
-    processor.execute();
+    processor.execute().unwrap();
 
     // ABCD -> Bogus opcode, but should be read on next fetch.   
-    processor.fetch();
+    processor.fetch().unwrap();
     assert_eq!(processor.opcode, 0xABCD); 
 }
 
 #[test]
 fn test_add_immediate() {
-    let mut screen: Screen = Screen::new(None);
+    let mut screen: Screen = Screen::new();
     let mut processor: Processor = Processor::new(&mut screen);
 
     // 63AA -> Set register V[3] to 0xAA
@@ -270,15 +278,1513 @@ fn test_add_immediate() {
     // CDEF -> Bogus opcode, should be skipped since V[3] != V[4]
     // 9350 -> Compare V[3] == V[5]. These values are equal and so do not skip
     // ABCD -> Bogus opcode, but should be read on next fetch.
-    processor.load(vec![0x63, 0x01, 0x73, 0x04, 0x64, 0xFF, 0x74, 0x01]);
+    processor.load(vec![0x63, 0x01, 0x73, 0x04, 0x64, 0xFF, 0x74, 0x01]).unwrap();
 
-    processor.cycle_cpu();
-    processor.cycle_cpu();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
 
     assert_eq!(processor.V[3], 0x05);
 
-    processor.cycle_cpu();
-    processor.cycle_cpu();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
 
     assert_eq!(processor.V[4], 0x00);
-}
\ No newline at end of file
+}
+#[test]
+fn test_subtract_vy_vx() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6105 -> Set register V[1] to 0x05
+    // 6208 -> Set register V[2] to 0x08
+    // 8127 -> V[1] = V[2] - V[1], no borrow so V[F] = 1
+    // 8217 -> V[2] = V[1] - V[2], borrow so V[F] = 0
+    processor.load(vec![0x61, 0x05, 0x62, 0x08, 0x81, 0x27, 0x82, 0x17]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x1], 0x03);
+    assert_eq!(processor.V[0x2], 0x08);
+    assert_eq!(processor.V[0xF], 1);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x2], 0xFB);
+    assert_eq!(processor.V[0xF], 0);
+}
+
+#[test]
+fn test_register_logic() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 61F0 -> Set register V[1] to 0xF0
+    // 623C -> Set register V[2] to 0x3C
+    // 8320 -> V[3] = V[2]
+    // 8311 -> V[3] |= V[1]
+    // 8412 -> V[4] = V[1], then 8422 -> V[4] &= V[2]
+    // 8513 -> V[5] = V[1], then 8523 -> V[5] ^= V[2]
+    processor.quirks.logic_resets_vf = false;
+    processor.load(vec![
+        0x61, 0xF0, 0x62, 0x3C, 0x83, 0x20, 0x83, 0x11,
+        0x84, 0x10, 0x84, 0x22, 0x85, 0x10, 0x85, 0x23,
+    ]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x3], 0x3C);
+
+    for _ in 0..5 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x3], 0xFC);
+    assert_eq!(processor.V[0x4], 0x30);
+    assert_eq!(processor.V[0x5], 0xCC);
+    assert_eq!(processor.V[0xF], 0);
+}
+
+#[test]
+fn test_add_registers() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 61F0 -> Set register V[1] to 0xF0
+    // 6210 -> Set register V[2] to 0x10
+    // 6301 -> Set register V[3] to 0x01
+    // 8134 -> V[1] += V[3], no carry so V[F] = 0
+    // 8124 -> V[1] += V[2], carries so V[F] = 1
+    processor.load(vec![0x61, 0xF0, 0x62, 0x10, 0x63, 0x01, 0x81, 0x34, 0x81, 0x24]).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x1], 0xF1);
+    assert_eq!(processor.V[0xF], 0);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x1], 0x01);
+    assert_eq!(processor.V[0xF], 1);
+
+    // 6FFF -> Set register V[F] to 0xFF
+    // 8F24 -> V[F] += V[2], the carry flag overwrites the sum
+    processor.load(vec![0x6F, 0xFF, 0x8F, 0x24]).unwrap();
+    processor.pc = 0x200;
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0xF], 1);
+}
+
+#[test]
+fn test_subtract_vx_vy() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6105 -> Set register V[1] to 0x05
+    // 6205 -> Set register V[2] to 0x05
+    // 6308 -> Set register V[3] to 0x08
+    // 8125 -> V[1] = V[1] - V[2], equal so no borrow and V[F] = 1
+    // 8135 -> V[1] = V[1] - V[3], borrow so V[F] = 0
+    // 8325 -> V[3] = V[3] - V[2], no borrow so V[F] = 1
+    processor.load(vec![0x61, 0x05, 0x62, 0x05, 0x63, 0x08, 0x81, 0x25, 0x81, 0x35, 0x83, 0x25]).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x1], 0x00);
+    assert_eq!(processor.V[0xF], 1);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x1], 0xF8);
+    assert_eq!(processor.V[0xF], 0);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x3], 0x03);
+    assert_eq!(processor.V[0xF], 1);
+
+    // 6F02 -> Set register V[F] to 0x02
+    // 8F25 -> V[F] = V[F] - V[2], borrows so the flag 0 overwrites 0xFD
+    // 6F09 -> Set register V[F] to 0x09
+    // 8F25 -> V[F] = V[F] - V[2], no borrow so the flag 1 overwrites 0x04
+    processor.load(vec![0x6F, 0x02, 0x8F, 0x25, 0x6F, 0x09, 0x8F, 0x25]).unwrap();
+    processor.pc = 0x200;
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0xF], 0);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0xF], 1);
+}
+
+#[test]
+fn test_subtract_vy_vx_flag() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6105 -> Set register V[1] to 0x05
+    // 6205 -> Set register V[2] to 0x05
+    // 8127 -> V[1] = V[2] - V[1], equal so no borrow and V[F] = 1
+    // 6F09 -> Set register V[F] to 0x09
+    // 8F17 -> V[F] = V[1] - V[F], borrows so the flag 0 overwrites 0xF7
+    processor.load(vec![0x61, 0x05, 0x62, 0x05, 0x81, 0x27, 0x6F, 0x09, 0x8F, 0x17]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x1], 0x00);
+    assert_eq!(processor.V[0xF], 1);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0xF], 0);
+}
+
+#[test]
+fn test_timers() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6130 -> Set register V[1] to 0x30
+    // F115 -> Set the delay timer to V[1]
+    // F118 -> Set the sound timer to V[1]
+    // F207 -> Set register V[2] to the delay timer
+    processor.load(vec![0x61, 0x30, 0xF1, 0x15, 0xF1, 0x18, 0xF2, 0x07]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.delay_timer, 0x30);
+    assert_eq!(processor.sound_timer, 0x30);
+
+    processor.tick();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x2], 0x2F);
+    assert_eq!(processor.sound_timer, 0x2F);
+}
+
+#[test]
+fn test_jump_with_offset() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6010 -> Set register V[0] to 0x10
+    // B300 -> Jump to 0x300 + V[0]
+    processor.load(vec![0x60, 0x10, 0xB3, 0x00]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.pc, 0x310);
+}
+
+#[test]
+fn test_random() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // C40F -> V[4] = random & 0x0F
+    // C500 -> V[5] = random & 0x00
+    processor.load(vec![0xC4, 0x0F, 0xC5, 0x00]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x4] & 0xF0, 0);
+    assert_eq!(processor.V[0x5], 0);
+}
+
+#[test]
+fn test_skip_if_key() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6A0B -> Set register V[A] to 0x0B
+    // EA9E -> Skip since key 0xB is pressed
+    // CDEF -> Bogus opcode, should be skipped
+    // EAA1 -> Do not skip since key 0xB is pressed
+    // ABCD -> Bogus opcode, should be read on next fetch
+    processor.load(vec![0x6A, 0x0B, 0xEA, 0x9E, 0xCD, 0xEF, 0xEA, 0xA1, 0xAB, 0xCD]).unwrap();
+    processor.keypad.press(0xB);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    processor.fetch().unwrap();
+    assert_eq!(processor.opcode, 0xABCD);
+}
+
+#[test]
+fn test_skip_if_not_key() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6A0B -> Set register V[A] to 0x0B
+    // EAA1 -> Skip since key 0xB is not pressed
+    // CDEF -> Bogus opcode, should be skipped
+    // EA9E -> Do not skip since key 0xB is not pressed
+    // ABCD -> Bogus opcode, should be read on next fetch
+    processor.load(vec![0x6A, 0x0B, 0xEA, 0xA1, 0xCD, 0xEF, 0xEA, 0x9E, 0xAB, 0xCD]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    processor.fetch().unwrap();
+    assert_eq!(processor.opcode, 0xABCD);
+}
+
+#[test]
+fn test_wait_for_key() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // F30A -> Wait for a key press and store it in V[3]
+    processor.load(vec![0xF3, 0x0A]).unwrap();
+
+    // No key is pressed so we should keep executing the same instruction.
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.pc, 0x200);
+
+    processor.keypad.press(0x7);
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.pc, 0x202);
+    assert_eq!(processor.V[0x3], 0x7);
+}
+
+#[test]
+fn test_add_to_index() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // A300 -> Set I to 0x300
+    // 6220 -> Set register V[2] to 0x20
+    // F21E -> I += V[2]
+    processor.load(vec![0xA3, 0x00, 0x62, 0x20, 0xF2, 0x1E]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.I, 0x320);
+}
+
+#[test]
+fn test_set_index_font() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 650A -> Set register V[5] to 0x0A
+    // F529 -> Set I to the sprite for digit A
+    processor.load(vec![0x65, 0x0A, 0xF5, 0x29]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.I, 0x50 + 0xA * 5);
+}
+
+#[test]
+fn test_store_bcd() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 66FE -> Set register V[6] to 254
+    // A300 -> Set I to 0x300
+    // F633 -> Store the BCD of V[6] at I
+    processor.load(vec![0x66, 0xFE, 0xA3, 0x00, 0xF6, 0x33]).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.read_byte(0x300), 2);
+    assert_eq!(processor.read_byte(0x301), 5);
+    assert_eq!(processor.read_byte(0x302), 4);
+}
+
+#[test]
+fn test_store_and_load_registers() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6011, 6122, 6233 -> Set V[0..=2]
+    // A300 -> Set I to 0x300
+    // F255 -> Store V[0..=2] at I
+    // 6000, 6100, 6200 -> Clear V[0..=2]
+    // F165 -> Load V[0..=1] from I
+    processor.load(vec![
+        0x60, 0x11, 0x61, 0x22, 0x62, 0x33, 0xA3, 0x00, 0xF2, 0x55,
+        0x60, 0x00, 0x61, 0x00, 0x62, 0x00, 0xF1, 0x65,
+    ]).unwrap();
+
+    for _ in 0..5 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.read_byte(0x300), 0x11);
+    assert_eq!(processor.read_byte(0x301), 0x22);
+    assert_eq!(processor.read_byte(0x302), 0x33);
+    assert_eq!(processor.read_byte(0x303), 0x00);
+    assert_eq!(processor.I, 0x300);
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x0], 0x11);
+    assert_eq!(processor.V[0x1], 0x22);
+    assert_eq!(processor.V[0x2], 0x00);
+}
+
+#[test]
+fn test_default_font() {
+    let mut screen: Screen = Screen::new();
+    let processor: Processor = Processor::new(&mut screen);
+
+    // Sprite for the digit 0
+    assert_eq!(processor.read_byte(0x50), 0xF0);
+    assert_eq!(processor.read_byte(0x51), 0x90);
+
+    // Sprite for the digit F
+    assert_eq!(processor.read_byte(0x50 + 0xF * 5), 0xF0);
+    assert_eq!(processor.read_byte(0x50 + 0xF * 5 + 4), 0x80);
+}
+
+#[test]
+fn test_load_font() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load_font(&DREAM_6800_FONT);
+
+    // 6001 -> Set register V[0] to 0x01
+    // F029 -> Set I to the sprite for digit 1
+    processor.load(vec![0x60, 0x01, 0xF0, 0x29]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    for row in 0..5 {
+        assert_eq!(processor.read_byte(processor.I + row), 0x40);
+    }
+}
+
+#[test]
+fn test_key_release() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6A0B -> Set register V[A] to 0x0B
+    // EAA1 -> Skip since key 0xB was released
+    // CDEF -> Bogus opcode, should be skipped
+    // ABCD -> Bogus opcode, should be read on next fetch
+    processor.load(vec![0x6A, 0x0B, 0xEA, 0xA1, 0xCD, 0xEF, 0xAB, 0xCD]).unwrap();
+
+    processor.keypad.press(0xB);
+    processor.keypad.release(0xB);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    processor.fetch().unwrap();
+    assert_eq!(processor.opcode, 0xABCD);
+}
+
+#[test]
+fn test_tick() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.delay_timer = 2;
+    processor.sound_timer = 1;
+
+    processor.tick();
+
+    assert_eq!(processor.delay_timer, 1);
+    assert_eq!(processor.sound_timer, 0);
+
+    processor.tick();
+
+    assert_eq!(processor.delay_timer, 0);
+    assert_eq!(processor.sound_timer, 0);
+}
+
+#[test]
+fn test_save_and_load_state() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6A05 -> Set register V[A] to 0x05
+    // 2208 -> Call subroutine at 0x208
+    // 0000 -> Padding
+    // 0000 -> Padding
+    // 7A01 -> Add 0x01 to V[A]
+    // F029 -> Set I to the sprite for digit 0
+    // D005 -> Draw the sprite at (V[0], V[0])
+    processor.load(vec![0x6A, 0x05, 0x22, 0x08, 0x00, 0x00, 0x00, 0x00, 0x7A, 0x01, 0xF0, 0x29, 0xD0, 0x05]).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    processor.delay_timer = 30;
+
+    let state = processor.save_state();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0xA], 0x06);
+    assert_eq!(processor.screen.get_pixel(0, 0), 1);
+
+    processor.load_state(&state).unwrap();
+
+    assert_eq!(processor.pc, 0x208);
+    assert_eq!(processor.V[0xA], 0x05);
+    assert_eq!(processor.delay_timer, 30);
+    assert_eq!(processor.cycles, 2);
+    assert_eq!(processor.stack.frames(), [0x204]);
+    assert_eq!(processor.screen.get_pixel(0, 0), 0);
+
+    // Resuming from the snapshot should give the same result as before.
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0xA], 0x06);
+    assert_eq!(processor.screen.get_pixel(0, 0), 1);
+}
+
+#[test]
+fn test_save_state_bytes() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(vec![0x6A, 0x05, 0x22, 0x06, 0x00, 0x00, 0xA1, 0x23]).unwrap();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    processor.screen.set_pixel(3, 4, 1);
+
+    let state = processor.save_state();
+    let bytes = state.to_bytes();
+
+    assert_eq!(SaveState::from_bytes(&bytes), Ok(state));
+    assert_eq!(SaveState::from_bytes(&bytes[..bytes.len() - 1]), Err(SaveStateError::Truncated));
+    assert_eq!(SaveState::from_bytes(b"nope"), Err(SaveStateError::BadMagic));
+
+    let mut future = bytes.clone();
+    future[4] = 99;
+    assert_eq!(SaveState::from_bytes(&future), Err(SaveStateError::UnsupportedVersion(99)));
+}
+
+#[test]
+fn test_save_state_old_versions() {
+    let mut screen: Screen = Screen::new();
+    let processor: Processor = Processor::new(&mut screen);
+
+    let bytes = processor.save_state().to_bytes();
+
+    // Files from before the index was widened are rejected with their version.
+    for version in 1..VERSION {
+        let mut old = bytes.clone();
+        old[4] = version;
+
+        let err = SaveState::from_bytes(&old).unwrap_err();
+        assert_eq!(err, SaveStateError::UnsupportedVersion(version));
+        assert_eq!(err.to_string(), format!("save state version {} is not supported, expected {}", version, VERSION));
+    }
+}
+
+#[test]
+fn test_save_state_wide_index() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6001 -> Set V[0] to 0x01
+    // F01E -> Add V[0] to I
+    processor.load(vec![0x60, 0x01, 0xF0, 0x1E]).unwrap();
+    processor.I = 0xFFFF;
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.I, 0x10000);
+
+    let state = SaveState::from_bytes(&processor.save_state().to_bytes()).unwrap();
+    processor.I = 0;
+    processor.load_state(&state).unwrap();
+
+    assert_eq!(processor.I, 0x10000);
+}
+
+#[test]
+fn test_save_state_pc_past_memory() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // The last instruction in memory runs, leaving pc just past the end.
+    processor.load(assemble("ORG 0xFFFE\nCLS").unwrap()).unwrap();
+    processor.pc = 0xFFFE;
+    processor.cycle_cpu().unwrap();
+    processor.stack.push(0x10000).unwrap();
+
+    let state = SaveState::from_bytes(&processor.save_state().to_bytes()).unwrap();
+    assert_eq!(state.pc, 0x10000);
+    assert_eq!(state.stack, vec![0x10000]);
+
+    processor.load_state(&state).unwrap();
+    assert_eq!(processor.pc, 0x10000);
+}
+
+#[test]
+fn test_load_state_checks_framebuffer() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    let state = processor.save_state();
+
+    let mut short = state.clone();
+    short.pixels.pop();
+    assert_eq!(processor.load_state(&short), Err(SaveStateError::Invalid("framebuffer size")));
+
+    let mut narrow = state.clone();
+    narrow.pixels[3].pop();
+    assert_eq!(processor.load_state(&narrow), Err(SaveStateError::Invalid("framebuffer size")));
+
+    let mut bright = state.clone();
+    bright.pixels[0][0] = 4;
+    assert_eq!(processor.load_state(&bright), Err(SaveStateError::Invalid("pixel value")));
+
+    let mut planes = state.clone();
+    planes.planes = 4;
+    assert_eq!(processor.load_state(&planes), Err(SaveStateError::Invalid("plane mask")));
+
+    processor.load_state(&state).unwrap();
+}
+
+#[test]
+fn test_load_state_resets_flags() {
+    let source = "
+        DRW V0, V0, 1
+        LD V1, 0x01
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.display_wait = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    let state = processor.save_state();
+
+    // The sprite leaves the processor waiting for the next frame.
+    processor.cycle_cpu().unwrap();
+    let waiting = processor.save_state();
+    assert!(waiting.waiting_for_vblank);
+
+    // Neither the wait nor an exit carry over into an older snapshot.
+    processor.halted = true;
+    processor.load_state(&state).unwrap();
+    assert!(!processor.halted);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.cycles, 1);
+    assert_eq!(processor.pc, 0x202);
+
+    // A snapshot taken while waiting still waits after it is loaded.
+    processor.load_state(&waiting).unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.pc, 0x202);
+
+    processor.tick();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.V[0x1], 0x01);
+}
+
+#[test]
+fn test_pc_and_opcode_breakpoints() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6001 -> Set register V[0] to 0x01
+    // 6102 -> Set register V[1] to 0x02
+    // 2208 -> Call subroutine at 0x208
+    // 0000 -> Padding
+    // 00EE -> Return from subroutine
+    processor.load(vec![0x60, 0x01, 0x61, 0x02, 0x22, 0x08, 0x00, 0x00, 0x00, 0xEE]).unwrap();
+
+    processor.breakpoints.add_pc(0x202);
+    processor.breakpoints.add_opcode(0x2000, 0xF000);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+
+    // The cycle that reaches the breakpoint stops before running it.
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), Some(BreakReason::Pc(0x202)));
+    assert_eq!(processor.pc, 0x202);
+    assert_eq!(processor.V[0x1], 0x00);
+
+    // The break is only reported once.
+    assert_eq!(processor.take_break(), None);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.V[0x1], 0x02);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), Some(BreakReason::Opcode { pc: 0x204, opcode: 0x2208 }));
+
+    processor.breakpoints.clear();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+    assert_eq!(processor.pc, 0x208);
+}
+
+#[test]
+fn test_breakpoint_at_entry_point() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6001 -> Set register V[0] to 0x01
+    // 1200 -> Jump back to 0x200
+    processor.load(vec![0x60, 0x01, 0x12, 0x00]).unwrap();
+    processor.breakpoints.add_pc(0x200);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), Some(BreakReason::Pc(0x200)));
+    assert_eq!(processor.V[0x0], 0x00);
+    assert_eq!(processor.cycles, 0);
+
+    // Resuming runs the instruction, the breakpoint fires again on the next visit.
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+    assert_eq!(processor.V[0x0], 0x01);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), Some(BreakReason::Pc(0x200)));
+    assert_eq!(processor.cycles, 2);
+}
+
+#[test]
+fn test_memory_watchpoints() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 66FE -> Set register V[6] to 254
+    // A300 -> Set I to 0x300
+    // F633 -> Store the BCD of V[6] at I
+    // F065 -> Load V[0] from I
+    processor.load(vec![0x66, 0xFE, 0xA3, 0x00, 0xF6, 0x33, 0xF0, 0x65]).unwrap();
+
+    processor.breakpoints.watch_memory(0x301..=0x302, Access::Write);
+    processor.breakpoints.watch_memory(0x300..=0x300, Access::Read);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(
+        processor.take_break(),
+        Some(BreakReason::MemoryWrite { pc: 0x204, address: 0x301, old: 0, new: 5 }),
+    );
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(
+        processor.take_break(),
+        Some(BreakReason::MemoryRead { pc: 0x206, address: 0x300, value: 2 }),
+    );
+}
+
+#[test]
+fn test_register_watchpoints() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // 6001 -> Set register V[0] to 0x01
+    // 6001 -> Set register V[0] to 0x01 again, which is not a change
+    // A123 -> Set I to 0x123
+    processor.load(vec![0x60, 0x01, 0x60, 0x01, 0xA1, 0x23]).unwrap();
+
+    processor.breakpoints.watch_register(Register::V(0)).unwrap();
+    processor.breakpoints.watch_register(Register::I).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(
+        processor.take_break(),
+        Some(BreakReason::Register { pc: 0x200, register: Register::V(0), old: 0, new: 1 }),
+    );
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(
+        processor.take_break(),
+        Some(BreakReason::Register { pc: 0x204, register: Register::I, old: 0, new: 0x123 }),
+    );
+}
+
+#[test]
+fn test_watch_invalid_register() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(vec![0x60, 0x01, 0x60, 0x02]).unwrap();
+
+    assert!(processor.breakpoints.watch_register(Register::V(0x10)).is_err());
+    assert!(processor.breakpoints.registers.is_empty());
+
+    // One added to the list directly is skipped rather than crashing.
+    processor.breakpoints.registers.push(Register::V(0x20));
+    processor.breakpoints.watch_register(Register::V(0xF)).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.take_break(), None);
+    assert_eq!(processor.V[0x0], 0x01);
+}
+
+#[test]
+fn test_quirk_shift_uses_vy() {
+    let source = "
+        LD V1, 0x81
+        LD V2, 0x06
+        SHR V1, V2
+        SHL V3, V1
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // V[1] is shifted in place, V[2] is ignored
+    assert_eq!(processor.V[0x1], 0x40);
+    assert_eq!(processor.V[0xF], 1);
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.shift_uses_vy = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0x1], 0x03);
+    assert_eq!(processor.V[0xF], 0);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0x3], 0x06);
+    assert_eq!(processor.V[0xF], 0);
+}
+
+#[test]
+fn test_shift_flag_is_written_last() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("LD VF, 0x03\nSHR VF").unwrap()).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0xF], 1);
+}
+
+#[test]
+fn test_quirk_load_store_increments_i() {
+    let source = "
+        LD I, 0x300
+        LD V0, 0x01
+        LD V1, 0x02
+        LD [I], V1
+        LD V1, [I]
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..5 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.I, 0x300);
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.load_store_increments_i = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.I, 0x302);
+    assert_eq!(processor.read_byte(0x301), 0x02);
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.I, 0x304);
+}
+
+#[test]
+fn test_quirk_jump_uses_vx() {
+    let source = "
+        LD V0, 0x10
+        LD V3, 0x20
+        JP V0, 0x300
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.jump_uses_vx = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // B300 is read as BXNN with X = 3
+    assert_eq!(processor.pc, 0x320);
+}
+
+#[test]
+fn test_quirk_logic_resets_vf() {
+    let source = "
+        LD VF, 0x05
+        OR V0, V1
+        LD VF, 0x05
+        AND V0, V1
+        LD VF, 0x05
+        XOR V0, V1
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..2 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0xF], 0x05);
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.logic_resets_vf = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+        processor.cycle_cpu().unwrap();
+
+        assert_eq!(processor.V[0xF], 0);
+    }
+}
+
+#[test]
+fn test_quirk_wrap_sprites() {
+    // Draws the 8 pixel wide top row of the 0 glyph at (60, 31), half of it
+    // past the right edge and the bottom rows past the bottom edge.
+    let source = "
+        LD V0, 60
+        LD V1, 31
+        LD I, sprite
+        DRW V0, V1, 2
+        sprite: DB 0xFF, 0x81
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    let lit = processor.screen.pixels.count_ones();
+    assert_eq!(lit, 4);
+    assert_eq!(processor.screen.get_pixel(63, 31), 1);
+    assert_eq!(processor.screen.get_pixel(0, 31), 0);
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.wrap_sprites = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    let lit = processor.screen.pixels.count_ones();
+    assert_eq!(lit, 10);
+    assert_eq!(processor.screen.get_pixel(3, 31), 1);
+    assert_eq!(processor.screen.get_pixel(60, 0), 1);
+    assert_eq!(processor.screen.get_pixel(3, 0), 1);
+    assert_eq!(processor.screen.get_pixel(0, 0), 0);
+}
+
+#[test]
+fn test_quirk_display_wait() {
+    let source = "
+        LD F, V0
+        DRW V0, V0, 5
+        DRW V0, V0, 5
+        LD V1, 0x01
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks.display_wait = true;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // Nothing runs after the first sprite until the next frame.
+    assert_eq!(processor.pc, 0x204);
+    assert_eq!(processor.cycles, 2);
+    assert_eq!(processor.screen.get_pixel(0, 0), 1);
+
+    processor.tick();
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.pc, 0x206);
+    assert_eq!(processor.screen.get_pixel(0, 0), 0);
+}
+
+#[test]
+fn test_quirk_presets() {
+    assert_eq!(Quirks::preset("default"), Some(Quirks::default()));
+    assert_eq!(Quirks::preset("vip"), Some(Quirks::VIP));
+    assert_eq!(Quirks::preset("schip"), Some(Quirks::SUPER_CHIP));
+    assert_eq!(Quirks::preset("chip48"), Some(Quirks::CHIP_48));
+    assert_eq!(Quirks::preset("xochip"), Some(Quirks::XO_CHIP));
+    assert_eq!(Quirks::preset("cosmac"), None);
+}
+
+#[test]
+fn test_resolution_switch() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("HIGH\nLD V0, 127\nLD V1, 63\nLD F, V2\nDRW V0, V1, 1\nLOW").unwrap()).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    assert!(processor.screen.is_hires());
+    assert_eq!((processor.screen.width, processor.screen.height), (128, 64));
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.screen.get_pixel(127, 63), 1);
+
+    // Switching back clears the screen.
+    processor.cycle_cpu().unwrap();
+    assert!(!processor.screen.is_hires());
+    assert_eq!((processor.screen.width, processor.screen.height), (WIDTH, HEIGHT));
+    assert_eq!(processor.screen.pixels.count_ones(), 0);
+}
+
+#[test]
+fn test_scroll() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("SCD 3\nSCR\nSCL\nSCL").unwrap()).unwrap();
+    processor.screen.set_pixel(10, 0, 1);
+    processor.screen.set_pixel(2, HEIGHT - 1, 1);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.screen.get_pixel(10, 3), 1);
+    assert_eq!(processor.screen.get_pixel(10, 0), 0);
+    assert_eq!(processor.screen.pixels.count_ones(), 1);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.screen.get_pixel(14, 3), 1);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.screen.get_pixel(6, 3), 1);
+    assert_eq!(processor.screen.pixels.count_ones(), 1);
+}
+
+#[test]
+fn test_exit() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("EXIT\nLD V0, 1").unwrap()).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert!(processor.halted);
+    assert_eq!(processor.pc, 0x202);
+    assert_eq!(processor.V[0x0], 0);
+}
+
+#[test]
+fn test_large_sprite() {
+    let source = "
+        HIGH
+        LD V0, 120
+        LD I, sprite
+        DRW V0, V1, 0
+        DRW V0, V1, 0
+        EXIT
+    sprite:
+        DW 0x8001, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFFFF
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // The right half of the sprite is clipped at the edge.
+    assert_eq!(processor.screen.get_pixel(120, 0), 1);
+    assert_eq!(processor.screen.get_pixel(127, 0), 0);
+    assert_eq!(processor.screen.get_pixel(127, 15), 1);
+    assert_eq!(processor.screen.pixels.count_ones(), 9);
+    assert_eq!(processor.V[0xF], 0);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.screen.pixels.count_ones(), 0);
+    assert_eq!(processor.V[0xF], 1);
+}
+
+#[test]
+fn test_big_font() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("LD V0, 0x18\nLD HF, V0").unwrap()).unwrap();
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.I, 0xA0 + 8 * 10);
+    assert_eq!(processor.read_byte(processor.I + 2), 0xC3);
+}
+
+#[test]
+fn test_rpl_flags() {
+    let source = "
+        LD V0, 1
+        LD V1, 2
+        LD V2, 3
+        LD R, V1
+        LD V0, 0
+        LD V1, 0
+        LD V2, 0
+        LD V2, R
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..8 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // Only V0 and V1 were saved, V2 comes back from the unused flag.
+    assert_eq!(processor.V[..3], [1, 2, 0]);
+}
+
+#[test]
+fn test_save_state_keeps_resolution() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("HIGH\nLD V0, 7\nLD R, V0\nEXIT").unwrap()).unwrap();
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    processor.screen.set_pixel(100, 50, 1);
+
+    let state = SaveState::from_bytes(&processor.save_state().to_bytes()).unwrap();
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load_state(&state).unwrap();
+
+    assert!(processor.screen.is_hires());
+    assert!(processor.halted);
+    assert_eq!(processor.rpl[0], 7);
+    assert_eq!(processor.screen.get_pixel(100, 50), 1);
+}
+
+#[test]
+fn test_long_index() {
+    let source = "
+        LD V0, 1
+        SE V0, 1
+        LD I, LONG 0x1234
+        LD I, LONG 0xABCD
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    // The skip jumps over both words of the long load.
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.pc, 0x208);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.I, 0xABCD);
+    assert_eq!(processor.pc, 0x20C);
+}
+
+#[test]
+fn test_register_range() {
+    let source = "
+        LD I, 0x300
+        LD V2, 2
+        LD V3, 3
+        LD V4, 4
+        LD [I], V2-V4
+        LD V2-V4, [I]
+        LD V4-V2, [I]
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.quirks = Quirks::XO_CHIP;
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..5 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // I is left alone even with the load and store quirk.
+    assert_eq!(processor.I, 0x300);
+    assert_eq!(processor.read_byte(0x300), 2);
+    assert_eq!(processor.read_byte(0x302), 4);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.V[2..5], [2, 3, 4]);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.V[2..5], [4, 3, 2]);
+}
+
+#[test]
+fn test_planes() {
+    let source = "
+        LD I, sprite
+        PLANE 3
+        DRW V0, V0, 1
+        PLANE 2
+        SCR
+        CLS
+        EXIT
+    sprite:
+        DB 0x80, 0xC0
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    // The data for the second plane follows the data for the first.
+    assert_eq!(processor.screen.get_pixel(0, 0), 3);
+    assert_eq!(processor.screen.get_pixel(1, 0), 2);
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+
+    // Only the selected plane moves.
+    assert_eq!(processor.screen.get_pixel(0, 0), 1);
+    assert_eq!(processor.screen.get_pixel(1, 0), 0);
+    assert_eq!(processor.screen.get_pixel(4, 0), 2);
+    assert_eq!(processor.screen.get_pixel(5, 0), 2);
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.screen.pixels.count_ones(), 1);
+}
+
+#[test]
+fn test_audio_pattern() {
+    let source = "
+        LD I, pattern
+        AUDIO
+        LD V0, 112
+        PITCH V0
+    pattern:
+        DB 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08
+        DB 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+    assert_eq!(processor.pitch, 64);
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    let pattern = processor.audio_pattern.unwrap();
+    assert_eq!(pattern[0], 0x01);
+    assert_eq!(pattern[15], 0x10);
+    assert_eq!(processor.pitch, 112);
+    assert_eq!(crate::processor::processor::pattern_rate(processor.pitch), 8000.0);
+
+    let state = SaveState::from_bytes(&processor.save_state().to_bytes()).unwrap();
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load_state(&state).unwrap();
+
+    assert_eq!(processor.audio_pattern, Some(pattern));
+    assert_eq!(processor.pitch, 112);
+}
+
+/// Runs the program until it fails, giving up after the number of cycles.
+fn run_until_error(source: &str, cycles: usize) -> EmuError {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..cycles {
+        if let Err(err) = processor.cycle_cpu() {
+            return err;
+        }
+    }
+
+    panic!("program did not fail");
+}
+
+#[test]
+fn test_rom_too_large() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    let max = MEM_SIZE - 0x200;
+
+    assert_eq!(processor.load(vec![0; max]), Ok(()));
+    assert_eq!(processor.load(vec![0; max + 1]), Err(EmuError::RomTooLarge { size: max + 1, max }));
+}
+
+#[test]
+fn test_stack_errors() {
+    assert_eq!(run_until_error("LD V0, 1\nRET", 2), EmuError::StackUnderflow { pc: 0x202, opcode: 0x00EE });
+    assert_eq!(run_until_error("loop: CALL loop", 5000), EmuError::StackOverflow { pc: 0x200, opcode: 0x2200 });
+}
+
+#[test]
+fn test_invalid_opcode() {
+    assert_eq!(run_until_error("CLS\nDW 0xE0FF", 2), EmuError::InvalidOpcode { pc: 0x202, opcode: 0xE0FF });
+}
+
+#[test]
+fn test_pc_out_of_bounds() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    // The last instruction in memory runs, the next fetch is past the end.
+    processor.load(assemble("ORG 0xFFFE\nCLS").unwrap()).unwrap();
+    processor.pc = 0xFFFE;
+
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.cycle_cpu(), Err(EmuError::PcOutOfBounds { pc: 0x10000, opcode: 0x00E0 }));
+}
+
+#[test]
+fn test_memory_out_of_bounds() {
+    let err = run_until_error("LD I, LONG 0xFFFF\nLD V1, [I]", 2);
+
+    assert_eq!(err, EmuError::MemoryOutOfBounds { pc: 0x204, opcode: 0xF165, address: 0x10000 });
+    assert_eq!(err.to_string(), "F165 at 0x204 accessed 0x10000 outside memory");
+
+    // Drawing a sprite that runs past the end of memory fails the same way.
+    let err = run_until_error("LD I, LONG 0xFFFE\nDRW V0, V0, 4", 2);
+
+    assert_eq!(err, EmuError::MemoryOutOfBounds { pc: 0x204, opcode: 0xD004, address: 0x10000 });
+}
+
+#[test]
+fn test_call_stack_depth() {
+    let rom = std::fs::read("roms/recursion.ch8").unwrap();
+
+    // The ROM recurses exactly as deep as the default stack allows.
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(rom.clone()).unwrap();
+
+    let mut deepest = 0;
+    for _ in 0..500 {
+        processor.cycle_cpu().unwrap();
+        deepest = deepest.max(processor.stack.len());
+    }
+
+    assert_eq!(deepest, 16);
+    assert!(processor.stack.is_empty());
+
+    // The COSMAC VIP only had room for 12 return addresses.
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(rom).unwrap();
+    processor.stack = CallStack::new(12);
+
+    let err = (0..500).find_map(|_| processor.cycle_cpu().err()).unwrap();
+
+    assert_eq!(err, EmuError::StackOverflow { pc: 0x22A, opcode: 0x2222 });
+    assert_eq!(processor.stack.len(), 12);
+}
+
+#[test]
+fn test_call_stack_frames() {
+    let source = "
+        CALL one
+    one:    CALL two
+    two:    CALL three
+    three:  JP three
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    for _ in 0..3 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.stack.frames(), [0x202, 0x204, 0x206]);
+}
+
+#[test]
+fn test_unknown_opcode_policy() {
+    let source = "
+        DW 0xE0FF
+        DW 0xE0FF
+        DW 0xFFFF
+        LD V0, 1
+    ";
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(source).unwrap()).unwrap();
+
+    // Unknown opcodes trap by default.
+    assert_eq!(processor.cycle_cpu(), Err(EmuError::InvalidOpcode { pc: 0x200, opcode: 0xE0FF }));
+    assert_eq!(processor.opcode_counters.trapped, 1);
+
+    processor.pc = 0x200;
+    processor.unknown_policy = OpcodePolicy::Log;
+
+    for _ in 0..4 {
+        processor.cycle_cpu().unwrap();
+    }
+
+    assert_eq!(processor.V[0], 1);
+    assert_eq!(processor.opcode_counters.logged, 3);
+
+    // Each opcode is only recorded the first time it is skipped.
+    assert_eq!(processor.take_logged_opcodes(), vec![
+        LoggedOpcode { pc: 0x200, opcode: 0xE0FF },
+        LoggedOpcode { pc: 0x204, opcode: 0xFFFF },
+    ]);
+    assert!(processor.take_logged_opcodes().is_empty());
+
+    processor.pc = 0x200;
+    processor.unknown_policy = OpcodePolicy::Ignore;
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.opcode_counters, OpcodeCounters { trapped: 1, logged: 3, ignored: 1, hooked: 0 });
+}
+
+#[test]
+fn test_sys_policy() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("SYS 0x123\nSYS 0x456").unwrap()).unwrap();
+
+    // Machine code calls are skipped by default.
+    processor.cycle_cpu().unwrap();
+    assert_eq!(processor.opcode_counters.ignored, 1);
+
+    processor.sys_policy = OpcodePolicy::Trap;
+    assert_eq!(processor.cycle_cpu(), Err(EmuError::InvalidOpcode { pc: 0x202, opcode: 0x0456 }));
+}
+
+#[test]
+fn test_sys_hook() {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("SYS 0x300").unwrap()).unwrap();
+    processor.sys_policy = OpcodePolicy::Trap;
+    processor.sys_hook = Some(Box::new(|address, registers, memory| {
+        registers[0] = (address >> 8) as u8;
+        memory[address] = 0xAB;
+    }));
+
+    processor.cycle_cpu().unwrap();
+
+    assert_eq!(processor.V[0], 3);
+    assert_eq!(processor.read_byte(0x300), 0xAB);
+    assert_eq!(processor.opcode_counters.hooked, 1);
+}
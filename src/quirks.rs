@@ -0,0 +1,92 @@
+/// Names of the quirk presets accepted by Quirks::preset.
+pub const PRESETS: [&str; 5] = ["default", "vip", "chip48", "schip", "xochip"];
+
+/// Behaviors that differ between CHIP-8 interpreters. ROMs are usually
+/// written against one of them, so they can be switched to match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quirks {
+
+    /// 8XY6 and 8XYE shift V[y] into V[x] instead of shifting V[x] in place.
+    pub shift_uses_vy: bool,
+
+    /// FX55 and FX65 leave I pointing after the last register stored or loaded.
+    pub load_store_increments_i: bool,
+
+    /// BNNN jumps to XNN plus V[x] instead of NNN plus V[0].
+    pub jump_uses_vx: bool,
+
+    /// 8XY1, 8XY2 and 8XY3 reset VF to zero.
+    pub logic_resets_vf: bool,
+
+    /// Sprites drawn past the edge of the screen wrap around to the other
+    /// side instead of being clipped.
+    pub wrap_sprites: bool,
+
+    /// DXYN waits for the next frame after drawing, so at most one sprite
+    /// is drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+
+    /// Returns the preset with the given name, see PRESETS.
+    pub fn preset(name: &str) -> Option<Quirks> {
+        match name {
+            "default" => Some(Quirks::default()),
+            "vip" => Some(Quirks::VIP),
+            "chip48" => Some(Quirks::CHIP_48),
+            "schip" => Some(Quirks::SUPER_CHIP),
+            "xochip" => Some(Quirks::XO_CHIP),
+            _ => None,
+        }
+    }
+
+    /// The original COSMAC VIP interpreter.
+    pub const VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: true,
+        wrap_sprites: false,
+        display_wait: true,
+    };
+
+    /// CHIP-48 on the HP-48 calculators.
+    pub const CHIP_48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: false,
+        jump_uses_vx: true,
+        logic_resets_vf: false,
+        wrap_sprites: false,
+        display_wait: false,
+    };
+
+    /// SUPER-CHIP 1.1, which kept the CHIP-48 behaviors.
+    pub const SUPER_CHIP: Quirks = Quirks::CHIP_48;
+
+    /// XO-CHIP as implemented by Octo.
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: true,
+        jump_uses_vx: false,
+        logic_resets_vf: false,
+        wrap_sprites: true,
+        display_wait: false,
+    };
+}
+
+impl Default for Quirks {
+
+    /// The behavior most modern ROMs expect: shifts and loads work in place
+    /// on V[x] and I, BNNN uses V[0] and sprites are clipped at the edges.
+    fn default() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            wrap_sprites: false,
+            display_wait: false,
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt;
+
+/// Identifies a save state file.
+const MAGIC: &[u8; 4] = b"C8SS";
+
+/// Version of the format written by to_bytes, bumped whenever the layout
+/// changes. Older versions are rejected rather than migrated.
+pub const VERSION: u8 = 5;
+
+/// Errors that can occur while reading a save state.
+#[derive(Debug, PartialEq)]
+pub enum SaveStateError {
+    /// The data does not start with the save state magic bytes.
+    BadMagic,
+
+    /// The save state was written by a newer or older incompatible version.
+    UnsupportedVersion(u8),
+
+    /// The data ended before the whole state was read.
+    Truncated,
+
+    /// A field holds a value the emulator cannot use.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveStateError::BadMagic => write!(f, "not a save state file"),
+            SaveStateError::UnsupportedVersion(v) => {
+                write!(f, "save state version {} is not supported, expected {}", v, VERSION)
+            },
+            SaveStateError::Truncated => write!(f, "save state is truncated"),
+            SaveStateError::Invalid(field) => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl std::error::Error for SaveStateError {}
+
+/// Snapshot of the full machine state, taken with Processor::save_state
+/// and restored with Processor::load_state.
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveState {
+    pub pc: usize,
+    pub I: usize,
+    pub opcode: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub cycles: u64,
+    pub V: [u8; 16],
+
+    /// SUPER-CHIP RPL user flags.
+    pub rpl: [u8; 16],
+
+    /// Whether the program has exited with 00FD.
+    pub halted: bool,
+
+    /// Whether DXYN is waiting for the next frame with the display wait quirk.
+    pub waiting_for_vblank: bool,
+
+    /// XO-CHIP audio pattern and pitch register.
+    pub audio_pattern: Option<[u8; 16]>,
+    pub pitch: u8,
+
+    /// Return addresses on the stack, from the bottom up.
+    pub stack: Vec<usize>,
+
+    pub memory: Vec<u8>,
+
+    pub width: usize,
+    pub height: usize,
+
+    /// Framebuffer rows, one value per pixel with a bit per bitplane.
+    pub pixels: Vec<Vec<usize>>,
+
+    /// Bitplanes selected for drawing.
+    pub planes: usize,
+}
+
+impl SaveState {
+
+    /// Encodes the state in the versioned binary format. Multi-byte values
+    /// are stored little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.memory.len() + self.width * self.height);
+
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+
+        out.extend_from_slice(&(self.pc as u32).to_le_bytes());
+        out.extend_from_slice(&(self.I as u32).to_le_bytes());
+        out.extend_from_slice(&self.opcode.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&self.cycles.to_le_bytes());
+        out.extend_from_slice(&self.V);
+        out.extend_from_slice(&self.rpl);
+        out.push(self.halted as u8);
+        out.push(self.waiting_for_vblank as u8);
+
+        match &self.audio_pattern {
+            Some(pattern) => {
+                out.push(1);
+                out.extend_from_slice(pattern);
+            },
+            None => out.push(0),
+        }
+        out.push(self.pitch);
+
+        out.extend_from_slice(&(self.stack.len() as u16).to_le_bytes());
+        for &address in &self.stack {
+            out.extend_from_slice(&(address as u32).to_le_bytes());
+        }
+
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+
+        out.extend_from_slice(&(self.width as u16).to_le_bytes());
+        out.extend_from_slice(&(self.height as u16).to_le_bytes());
+        for row in &self.pixels {
+            out.extend(row.iter().map(|&v| v as u8));
+        }
+        out.push(self.planes as u8);
+
+        out
+    }
+
+    /// Decodes a state written by to_bytes.
+    pub fn from_bytes(data: &[u8]) -> Result<SaveState, SaveStateError> {
+        let mut reader = Reader { data, pos: 0 };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SaveStateError::BadMagic);
+        }
+
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(SaveStateError::UnsupportedVersion(version));
+        }
+
+        let pc = reader.u32()? as usize;
+        let index = reader.u32()? as usize;
+        let opcode = reader.u16()?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let cycles = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+
+        let mut registers = [0; 16];
+        registers.copy_from_slice(reader.take(16)?);
+
+        let mut rpl = [0; 16];
+        rpl.copy_from_slice(reader.take(16)?);
+
+        let halted = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SaveStateError::Invalid("halted flag")),
+        };
+        let waiting_for_vblank = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SaveStateError::Invalid("display wait flag")),
+        };
+
+        let audio_pattern = match reader.u8()? {
+            0 => None,
+            1 => Some(reader.take(16)?.try_into().unwrap()),
+            _ => return Err(SaveStateError::Invalid("audio pattern")),
+        };
+        let pitch = reader.u8()?;
+
+        let stack_len = reader.u16()? as usize;
+        let stack = (0..stack_len)
+            .map(|_| reader.u32().map(|address| address as usize))
+            .collect::<Result<Vec<usize>, SaveStateError>>()?;
+
+        let memory_len = reader.u32()? as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        let pixels = (0..height)
+            .map(|_| reader.take(width).map(|row| row.iter().map(|&v| v as usize).collect()))
+            .collect::<Result<Vec<Vec<usize>>, SaveStateError>>()?;
+        let planes = reader.u8()? as usize;
+
+        if reader.pos != data.len() {
+            return Err(SaveStateError::Invalid("length"));
+        }
+
+        Ok(SaveState {
+            pc,
+            I: index,
+            opcode,
+            delay_timer,
+            sound_timer,
+            cycles,
+            V: registers,
+            rpl,
+            halted,
+            waiting_for_vblank,
+            audio_pattern,
+            pitch,
+            stack,
+            memory,
+            width,
+            height,
+            pixels,
+            planes,
+        })
+    }
+}
+
+/// Cursor over the save state bytes.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SaveStateError> {
+        let end = self.pos.checked_add(len).ok_or(SaveStateError::Truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or(SaveStateError::Truncated)?;
+
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+/// Default number of instructions executed every second.
+pub const DEFAULT_SPEED: u32 = 700;
+
+/// Rate at which the timers are decremented and the screen is presented.
+pub const FRAME_RATE: u32 = 60;
+
+/// Most frames we will run back to back after the host stalls, anything
+/// beyond this is dropped so we do not fast forward through the game.
+pub const MAX_CATCH_UP_FRAMES: u32 = 6;
+
+/// Splits wall-clock time into 60hz frames and decides how many
+/// instructions to run in each frame.
+pub struct Scheduler {
+    /// Number of instructions executed every second.
+    instructions_per_second: u32,
+
+    /// Duration of a single frame.
+    frame_time: Duration,
+
+    /// Time at which the next frame is due.
+    next_frame: Instant,
+
+    /// Number of frames run so far, used to spread instructions evenly
+    /// when the speed is not a multiple of the frame rate.
+    frame_count: u64,
+}
+
+impl Scheduler {
+
+    pub fn new(instructions_per_second: u32, now: Instant) -> Self {
+        let frame_time = Duration::from_secs(1) / FRAME_RATE;
+
+        Scheduler {
+            instructions_per_second,
+            frame_time,
+            next_frame: now + frame_time,
+            frame_count: 0,
+        }
+    }
+
+    /// Returns how many frames are due at the given time and advances the
+    /// schedule past them. At most MAX_CATCH_UP_FRAMES are returned, the
+    /// remaining missed frames are skipped.
+    pub fn frames_due(&mut self, now: Instant) -> u32 {
+        let mut frames = 0;
+
+        while self.next_frame <= now {
+            if frames == MAX_CATCH_UP_FRAMES {
+                self.next_frame = now + self.frame_time;
+                break;
+            }
+
+            self.next_frame += self.frame_time;
+            frames += 1;
+        }
+
+        frames
+    }
+
+    /// Returns the number of instructions to execute in the next frame and
+    /// counts the frame as run.
+    pub fn next_frame_instructions(&mut self) -> u32 {
+        let ips = self.instructions_per_second as u64;
+        let rate = FRAME_RATE as u64;
+
+        let done = ips * self.frame_count / rate;
+        self.frame_count += 1;
+
+        (ips * self.frame_count / rate - done) as u32
+    }
+
+    /// Time left until the next frame is due, zero if it is already due.
+    pub fn time_until_next_frame(&self, now: Instant) -> Duration {
+        self.next_frame.saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,42 @@
+use std::time::{Duration, Instant};
+
+use crate::scheduler::{Scheduler, MAX_CATCH_UP_FRAMES};
+
+const FRAME: Duration = Duration::from_nanos(16_666_666);
+
+#[test]
+fn test_frames_due() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(600, start);
+
+    assert_eq!(scheduler.frames_due(start), 0);
+    assert_eq!(scheduler.frames_due(start + FRAME / 2), 0);
+    assert_eq!(scheduler.frames_due(start + FRAME), 1);
+    assert_eq!(scheduler.frames_due(start + FRAME * 3), 2);
+}
+
+#[test]
+fn test_catch_up_is_limited() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(600, start);
+
+    // The host stalled for a full second, only a few frames are run back to back.
+    let now = start + Duration::from_secs(1);
+    assert_eq!(scheduler.frames_due(now), MAX_CATCH_UP_FRAMES);
+
+    // The skipped frames are dropped instead of being run later.
+    assert_eq!(scheduler.frames_due(now), 0);
+    assert_eq!(scheduler.frames_due(now + FRAME), 1);
+}
+
+#[test]
+fn test_instructions_per_frame() {
+    let mut scheduler = Scheduler::new(700, Instant::now());
+
+    // 700 does not divide evenly into 60 frames, but every second should
+    // still run exactly 700 instructions.
+    let per_frame: Vec<u32> = (0..60).map(|_| scheduler.next_frame_instructions()).collect();
+
+    assert_eq!(per_frame.iter().sum::<u32>(), 700);
+    assert!(per_frame.iter().all(|&n| n == 11 || n == 12));
+}
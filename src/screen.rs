@@ -2,123 +2,223 @@
 
 use crate::WIDTH;
 use crate::HEIGHT;
+use crate::HIRES_WIDTH;
+use crate::HIRES_HEIGHT;
 use crate::SCALE;
 
-use sdl2::rect::Rect;
-use sdl2::pixels::Color;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
-pub struct Screen<'a> {
-    pub width: usize,
-    pub height: usize,
-    pub pixels: Vec<Vec<usize>>,
-    pub canvas: Option<&'a mut Canvas<Window>>,
-    pub update_screen: bool,
+use crate::display::Display;
+use crate::framebuffer::Framebuffer;
+use crate::png::write_png;
+
+/// File formats the framebuffer can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Pbm,
 }
 
-impl<'a> Screen<'a> {
+impl ImageFormat {
 
-    pub fn new(canvas: Option<&'a mut Canvas<Window>>) -> Screen {
-        let pixels: Vec<Vec<usize>> = vec![vec![0; WIDTH as usize]; HEIGHT as usize];
-        Screen { width: WIDTH, height: HEIGHT, pixels, canvas, update_screen: false }
-    }
+    /// Picks the format matching the extension of the path.
+    pub fn from_path(path: &str) -> Option<ImageFormat> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
 
-    pub fn setup(&mut self) {
-        if let Some(canvas) = &mut self.canvas {
-            canvas.set_draw_color(Color::RGB(0, 255, 255));
-            canvas.clear();
-            canvas.present();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "pbm" => Some(ImageFormat::Pbm),
+            _ => None,
         }
     }
+}
+
+/// Colors of the pixel values, indexed by the bitplanes that are set.
+pub const PALETTE: [(u8, u8, u8); 4] = [(0, 0, 0), (255, 255, 255), (255, 102, 0), (102, 34, 0)];
+
+pub struct Screen {
+    pub width: usize,
+    pub height: usize,
+    pub scale: usize,
+
+    /// Pixel values, each bit is one XO-CHIP bitplane. Plain CHIP-8 only uses
+    /// the first plane so every pixel is 0 or 1.
+    pub pixels: Framebuffer,
+
+    /// Mask of the bitplanes that drawing, clearing and scrolling apply to.
+    pub planes: usize,
+
+}
+
+impl Screen {
+
+    pub fn new() -> Screen {
+        let pixels = Framebuffer::new(WIDTH, HEIGHT);
+        Screen { width: WIDTH, height: HEIGHT, scale: SCALE, pixels, planes: 1 }
+    }
 
     pub fn get_scaled_width(&self) -> u32 {
-        (self.width * SCALE) as u32
+        (self.width * self.cell_size()) as u32
     }
 
     pub fn get_scaled_height(&self) -> u32 {
-        (self.height * SCALE) as u32
+        (self.height * self.cell_size()) as u32
+    }
+
+    /// Size of a pixel on the canvas. The scale is given for the low
+    /// resolution, so the window keeps its size in high resolution.
+    fn cell_size(&self) -> usize {
+        (self.scale * WIDTH / self.width).max(1)
+    }
+
+    /// True while the SUPER-CHIP 128x64 resolution is in use.
+    pub fn is_hires(&self) -> bool {
+        self.width == HIRES_WIDTH
+    }
+
+    /// Switches between the 64x32 and 128x64 resolutions, clearing the screen.
+    pub fn set_hires(&mut self, hires: bool) {
+        let (width, height) = if hires { (HIRES_WIDTH, HIRES_HEIGHT) } else { (WIDTH, HEIGHT) };
+        self.resize(width, height);
+    }
+
+    /// Changes the resolution, clearing the screen.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels = Framebuffer::new(width, height);
+    }
+
+    /// Moves the picture down by n rows, the rows scrolled in at the top are blank.
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll(0, n as isize);
+    }
+
+    /// Moves the picture up by n rows.
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll(0, -(n as isize));
+    }
+
+    /// Moves the picture right by n columns.
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll(n as isize, 0);
+    }
+
+    /// Moves the picture left by n columns.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll(-(n as isize), 0);
+    }
+
+    /// Moves the selected bitplanes by dx columns and dy rows, the other
+    /// planes stay where they are.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        self.pixels.scroll(dx, dy, self.planes);
     }
 
     pub fn get_pixel(&self, x: usize, y: usize) ->  usize {
-        self.pixels[y][x]
+        self.pixels.get(x, y)
     }
 
     pub fn update_pixel(&mut self, x: usize, y: usize) {
-        self.pixels[y as usize][x as usize] ^= 1;
+        self.pixels.toggle(x, y, 1);
+    }
+
+    /// Flips the pixel in the given bitplane mask.
+    pub fn toggle_pixel(&mut self, x: usize, y: usize, plane: usize) {
+        self.pixels.toggle(x, y, plane);
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, v: usize) {
-        self.pixels[y as usize][x as usize] = v;
+        self.pixels.set(x, y, v);
     }
 
     /// Updates every pixel to a random value.
     pub fn four_corners(&mut self) {
         self.set_pixel(0, 0, 1);
-        self.set_pixel(WIDTH - 1, HEIGHT - 1, 1);
-        self.set_pixel(WIDTH - 1, 0, 1);
-        self.set_pixel(0, HEIGHT - 1, 1);
+        self.set_pixel(self.width - 1, self.height - 1, 1);
+        self.set_pixel(self.width - 1, 0, 1);
+        self.set_pixel(0, self.height - 1, 1);
     }
 
-    pub fn clear(&mut self) {
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
-                self.set_pixel(x, y, 0);
+    /// Renders the framebuffer as text, one line per row with '#' for
+    /// pixels that are on and '.' for pixels that are off. Pixels only in
+    /// the second XO-CHIP bitplane are '+' and pixels in both are '%'.
+    pub fn to_ascii(&self) -> String {
+        let mut ascii = String::with_capacity((self.width + 1) * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                ascii.push(['.', '#', '+', '%'][self.get_pixel(x, y) & 3]);
             }
+            ascii.push('\n');
         }
 
-        // The rectangle we will use to fill with our color value.
-        let rect = Rect::new(
-            0,
-            0,
-            (WIDTH * SCALE) as u32,
-            (HEIGHT * SCALE) as u32,
-        );
+        ascii
+    }
 
-        if self.canvas.is_none() {
-            return
-        }
+    /// Writes the framebuffer as an image, each CHIP-8 pixel becomes a
+    /// square of scale by scale image pixels. PNG keeps the bitplane colors
+    /// of the palette, PBM is 1 bit so any lit pixel comes out black.
+    pub fn write_image<W: Write>(&self, out: &mut W, format: ImageFormat, scale: usize) -> io::Result<()> {
+        let width = self.width * scale;
+        let height = self.height * scale;
+
+        match format {
+            ImageFormat::Png => {
+                write_png(out, width, height, &PALETTE, |x, y| self.get_pixel(x / scale, y / scale) & 3)
+            },
+
+            // A plain netpbm bitmap, where 1 is black. We flip the bits so
+            // the image matches what is shown on screen.
+            ImageFormat::Pbm => {
+                writeln!(out, "P1")?;
+                writeln!(out, "{} {}", width, height)?;
+
+                for y in 0..height {
+                    let line: Vec<&str> = (0..width)
+                        .map(|x| if self.get_pixel(x / scale, y / scale) == 0 { "1" } else { "0" })
+                        .collect();
+                    writeln!(out, "{}", line.join(" "))?;
+                }
 
-        if let Some(canvas) = &mut self.canvas {
-            canvas.set_draw_color(Color::RGB(0, 0, 0));
-            canvas.fill_rect(rect).unwrap();
-            canvas.present();
+                Ok(())
+            },
         }
     }
 
-    pub fn draw(&mut self) {
+    /// Saves the framebuffer to a file, the format is picked from the extension.
+    pub fn save_image(&self, path: &str, scale: usize) -> io::Result<()> {
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "image file must end in .png or .pbm")
+        })?;
 
-        if self.canvas.is_none() {
-            return
-        }
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_image(&mut out, format, scale)?;
+        out.flush()
+    }
 
-        if let Some(canvas) = &mut self.canvas {
+    /// Clears the selected bitplanes.
+    pub fn clear(&mut self) {
+        self.pixels.clear(self.planes);
+    }
 
-            for x in 0..self.width {
-                for y in 0..self.height {
-    
-                    // Value to determine if we are drawing a black or white pixel.
-                    let v = self.pixels[y as usize][x as usize];
-                    
-                    // The rectangle we will use to fill with our color value.
-                    let rect = Rect::new(
-                        (x * SCALE) as i32,
-                        (y * SCALE) as i32,
-                        SCALE as u32,
-                        SCALE as u32,
-                    );
-    
-                    if v <= 0 {
-                        canvas.set_draw_color(Color::RGB(0, 0, 0));
-                    } else {
-                        canvas.set_draw_color(Color::RGB(255, 255, 255));
-                    }
-    
-                    canvas.fill_rect(rect).unwrap();
-                }
-            }
-    
-            canvas.present();
+    /// Shows the pixels that changed since the last call on the display,
+    /// does nothing when none have.
+    pub fn present(&mut self, display: &mut dyn Display) -> Result<(), String> {
+        match self.pixels.take_dirty() {
+            Some(dirty) => display.present(&self.pixels, dirty),
+            None => Ok(()),
         }
     }
-}
\ No newline at end of file
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::new()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,49 @@
+use super::*;
+
+/// Finds the IDAT chunk and returns the stored image rows after the zlib
+/// and deflate block headers.
+fn png_rows(png: &[u8]) -> &[u8] {
+    let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+    &png[idat + 7..]
+}
+
+#[test]
+fn test_png_keeps_plane_colors() {
+    let mut screen = Screen::new();
+
+    screen.set_pixel(0, 0, 1);
+    screen.set_pixel(1, 0, 2);
+    screen.set_pixel(2, 0, 3);
+
+    let mut out = Vec::new();
+    screen.write_image(&mut out, ImageFormat::Png, 1).unwrap();
+
+    // A 2 bit indexed image using the screen palette.
+    assert_eq!(&out[24..26], &[2, 3]);
+    let plte = out.windows(4).position(|w| w == b"PLTE").unwrap() + 4;
+    let colors: Vec<u8> = PALETTE.iter().flat_map(|&(r, g, b)| [r, g, b]).collect();
+    assert_eq!(&out[plte..plte + 12], colors.as_slice());
+
+    assert_eq!(&png_rows(&out)[..3], &[0, 0b01_10_11_00, 0]);
+}
+
+#[test]
+fn test_pbm_is_one_bit() {
+    let mut screen = Screen::new();
+
+    screen.set_pixel(0, 0, 1);
+    screen.set_pixel(1, 0, 2);
+    screen.set_pixel(2, 0, 3);
+
+    let mut out = Vec::new();
+    screen.write_image(&mut out, ImageFormat::Pbm, 1).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    let mut lines = text.lines();
+
+    assert_eq!(lines.next(), Some("P1"));
+    assert_eq!(lines.next(), Some("64 32"));
+
+    // Every lit pixel is black whichever planes it is in.
+    assert!(lines.next().unwrap().starts_with("0 0 0 1 "));
+}
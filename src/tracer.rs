@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+
+use crate::instruction::decode;
+
+/// State of the processor just before an instruction runs.
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEntry {
+    pub cycle: u64,
+    pub pc: usize,
+    pub opcode: u16,
+    pub I: usize,
+    pub V: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mnemonic = match decode(self.opcode) {
+            Ok(instruction) => instruction.to_string(),
+            Err(_) => "???".to_string(),
+        };
+
+        write!(f, "{:>8} {:03X} {:04X}  {:<18} I={:03X} V=", self.cycle, self.pc, self.opcode, mnemonic, self.I)?;
+
+        for v in self.V {
+            write!(f, "{:02X}", v)?;
+        }
+
+        write!(f, " DT={:02X} ST={:02X}", self.delay_timer, self.sound_timer)
+    }
+}
+
+/// Limits which instructions are traced.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceFilter {
+
+    /// Only trace instructions at addresses in this range.
+    pub pc: Option<RangeInclusive<usize>>,
+
+    /// Only trace opcodes whose first hex digit is in the list, all when empty.
+    pub classes: Vec<u8>,
+}
+
+impl TraceFilter {
+
+    /// Parses a hexadecimal address range like "200-2FF" and a list of
+    /// opcode classes like "1,2,D".
+    pub fn parse(pc: Option<&str>, classes: Option<&str>) -> Result<TraceFilter, String> {
+        let mut filter = TraceFilter::default();
+
+        if let Some(text) = pc {
+            let (start, end) = text.split_once('-').unwrap_or((text, text));
+            let parse = |address: &str| {
+                let digits = address.trim().trim_start_matches("0x").trim_start_matches("0X");
+                usize::from_str_radix(digits, 16).map_err(|_| format!("invalid trace address range '{}'", text))
+            };
+
+            let (start, end) = (parse(start)?, parse(end)?);
+
+            if start > end {
+                return Err(format!("invalid trace address range '{}', the start is after the end", text));
+            }
+
+            filter.pc = Some(start..=end);
+        }
+
+        if let Some(text) = classes {
+            for class in text.split(',') {
+                match u8::from_str_radix(class.trim(), 16) {
+                    Ok(class) if class < 16 => filter.classes.push(class),
+                    _ => return Err(format!("invalid opcode class '{}', expected 0 to F", class)),
+                }
+            }
+        }
+
+        Ok(filter)
+    }
+
+    /// True when the instruction should be traced.
+    pub fn matches(&self, entry: &TraceEntry) -> bool {
+        let class = (entry.opcode >> 12) as u8;
+
+        self.pc.as_ref().is_none_or(|range| range.contains(&entry.pc))
+            && (self.classes.is_empty() || self.classes.contains(&class))
+    }
+}
+
+/// Writes a line for every instruction the processor runs. In ring mode only
+/// the last instructions are kept, and they are written out by dump or when
+/// the tracer is dropped during a panic.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    pub filter: TraceFilter,
+    ring: Option<VecDeque<TraceEntry>>,
+    capacity: usize,
+    failed: bool,
+}
+
+impl Tracer {
+
+    /// Traces every instruction to the writer as it runs.
+    pub fn new(out: Box<dyn Write>) -> Self {
+        Tracer { out, filter: TraceFilter::default(), ring: None, capacity: 0, failed: false }
+    }
+
+    /// Keeps only the last capacity instructions until they are dumped.
+    pub fn ring(out: Box<dyn Write>, capacity: usize) -> Self {
+        Tracer {
+            out,
+            filter: TraceFilter::default(),
+            ring: Some(VecDeque::with_capacity(capacity)),
+            capacity,
+            failed: false,
+        }
+    }
+
+    /// Opens the file to trace to, in ring mode when a capacity is given.
+    pub fn create(path: &str, capacity: Option<usize>) -> io::Result<Self> {
+        let out = Box::new(BufWriter::new(File::create(path)?));
+
+        Ok(match capacity {
+            Some(capacity) => Tracer::ring(out, capacity),
+            None => Tracer::new(out),
+        })
+    }
+
+    /// Records the instruction if it passes the filter.
+    pub fn trace(&mut self, entry: TraceEntry) {
+        if !self.filter.matches(&entry) {
+            return;
+        }
+
+        match &mut self.ring {
+            Some(ring) => {
+                if ring.len() == self.capacity {
+                    ring.pop_front();
+                }
+
+                ring.push_back(entry);
+            },
+            None => {
+                let result = writeln!(self.out, "{}", entry);
+                self.check(result);
+            },
+        }
+    }
+
+    /// Writes out and clears the instructions kept in ring mode.
+    pub fn dump(&mut self) {
+        let entries: Vec<TraceEntry> = match &mut self.ring {
+            Some(ring) => ring.drain(..).collect(),
+            None => Vec::new(),
+        };
+
+        for entry in entries {
+            let result = writeln!(self.out, "{}", entry);
+            self.check(result);
+        }
+
+        let result = self.out.flush();
+        self.check(result);
+    }
+
+    /// Warns about the first failed write, the rest of the trace is lost anyway.
+    fn check(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            if !self.failed {
+                eprintln!("warning: could not write trace: {}", err);
+            }
+
+            self.failed = true;
+        }
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        if self.ring.is_some() && std::thread::panicking() {
+            eprintln!("emulator crashed, writing the last {} traced instructions", self.capacity);
+            self.dump();
+        } else {
+            let _ = self.out.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use crate::assembler::assemble;
+use crate::processor::processor::Processor;
+use crate::screen::Screen;
+use crate::tracer::{TraceEntry, TraceFilter, Tracer};
+
+/// Writer the test keeps a handle to after giving it to the tracer.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.borrow().clone()).unwrap().lines().map(str::to_string).collect()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const PROGRAM: &str = "
+        LD V0, 0x12
+        LD I, 0x300
+        CALL sub
+loop:   JP loop
+sub:    ADD V0, 1
+        RET
+";
+
+fn run(tracer: Tracer, cycles: usize) {
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble(PROGRAM).unwrap()).unwrap();
+    processor.tracer = Some(tracer);
+
+    for _ in 0..cycles {
+        processor.cycle_cpu().unwrap();
+    }
+}
+
+#[test]
+fn test_trace_line() {
+    let mut entry = TraceEntry { cycle: 42, pc: 0x204, opcode: 0xD125, I: 0x2A0, V: [0; 16], delay_timer: 0x3C, sound_timer: 0 };
+    entry.V[0xF] = 0x01;
+
+    assert_eq!(
+        entry.to_string(),
+        "      42 204 D125  DRW V1, V2, 5      I=2A0 V=00000000000000000000000000000001 DT=3C ST=00"
+    );
+}
+
+#[test]
+fn test_trace() {
+    let buffer = Buffer::default();
+    run(Tracer::new(Box::new(buffer.clone())), 6);
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("       0 200 6012  LD V0, 0x12        I=000 V=00"));
+    assert!(lines[2].starts_with("       2 204 2208  CALL 0x208         I=300 V=12"));
+    assert!(lines[5].starts_with("       5 206 1206  JP 0x206           I=300 V=13"));
+}
+
+#[test]
+fn test_filters() {
+    let buffer = Buffer::default();
+    let mut tracer = Tracer::new(Box::new(buffer.clone()));
+    tracer.filter = TraceFilter::parse(Some("204-20A"), Some("1,2")).unwrap();
+    run(tracer, 8);
+
+    let pcs: Vec<String> = buffer.lines().iter().map(|line| line[9..12].to_string()).collect();
+    assert_eq!(pcs, vec!["204", "206", "206", "206"]);
+
+    assert!(TraceFilter::parse(Some("20G"), None).is_err());
+    assert_eq!(
+        TraceFilter::parse(Some("0x300-0x200"), None).err(),
+        Some("invalid trace address range '0x300-0x200', the start is after the end".to_string()),
+    );
+    assert!(TraceFilter::parse(Some("300-300"), None).is_ok());
+    assert!(TraceFilter::parse(None, Some("1,10")).is_err());
+}
+
+#[test]
+fn test_ring_dumps_last_instructions() {
+    let buffer = Buffer::default();
+    let mut tracer = Tracer::ring(Box::new(buffer.clone()), 2);
+    tracer.trace(TraceEntry { cycle: 0, pc: 0x200, opcode: 0x00E0, I: 0, V: [0; 16], delay_timer: 0, sound_timer: 0 });
+    tracer.trace(TraceEntry { cycle: 1, pc: 0x202, opcode: 0x00E0, I: 0, V: [0; 16], delay_timer: 0, sound_timer: 0 });
+    tracer.trace(TraceEntry { cycle: 2, pc: 0x204, opcode: 0x00E0, I: 0, V: [0; 16], delay_timer: 0, sound_timer: 0 });
+    assert!(buffer.lines().is_empty());
+
+    tracer.dump();
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("       1 202"));
+    assert!(lines[1].starts_with("       2 204"));
+
+    // Nothing is written when the tracer is dropped normally.
+    drop(tracer);
+    assert_eq!(buffer.lines().len(), 2);
+}
+
+#[test]
+fn test_ring_dumps_on_panic() {
+    let buffer = Buffer::default();
+    let tracer = Tracer::ring(Box::new(buffer.clone()), 3);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut screen: Screen = Screen::new();
+        let mut processor: Processor = Processor::new(&mut screen);
+
+        processor.load(assemble(PROGRAM).unwrap()).unwrap();
+        processor.tracer = Some(tracer);
+
+        for _ in 0..5 {
+            processor.cycle_cpu().unwrap();
+        }
+
+        panic!("crash");
+    }));
+
+    assert!(result.is_err());
+
+    let cycles: Vec<String> = buffer.lines().iter().map(|line| line[..8].trim().to_string()).collect();
+    assert_eq!(cycles, vec!["2", "3", "4"]);
+}
+
+#[test]
+fn test_ring_dumps_on_error() {
+    let buffer = Buffer::default();
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(assemble("LD V0, 1\nLD V1, 2\nRET").unwrap()).unwrap();
+    processor.tracer = Some(Tracer::ring(Box::new(buffer.clone()), 2));
+
+    processor.cycle_cpu().unwrap();
+    processor.cycle_cpu().unwrap();
+    assert!(buffer.lines().is_empty());
+
+    assert!(processor.cycle_cpu().is_err());
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("       2 204 00EE"));
+}
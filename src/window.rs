@@ -0,0 +1,231 @@
+//! The SDL window frontend, only built with the `sdl` feature.
+
+use chip_8_rust::{HEIGHT, WIDTH};
+use chip_8_rust::debugger::{self, Action, Debugger};
+use chip_8_rust::display::{Display, SdlDisplay};
+use chip_8_rust::processor::error::EmuError;
+use chip_8_rust::processor::processor::Processor;
+use chip_8_rust::quirks::Quirks;
+use chip_8_rust::savestate::SaveState;
+use chip_8_rust::scheduler::Scheduler;
+use chip_8_rust::screen::Screen;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use std::fs;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::audio::{Beeper, NullBeeper, SdlBeeper};
+use crate::cli::Options;
+use crate::keymap::KeyMap;
+use crate::{configure, fail, report_logged_opcodes};
+
+/// Number of numbered save state slots available from the keyboard.
+const SAVE_SLOTS: u8 = 10;
+
+/// Opens a window and runs the program in it until the window is closed.
+pub fn run_window(options: &Options, rom: Vec<u8>, quirks: Quirks) {
+    let keymap = KeyMap::from_layout(&options.keys).unwrap_or_else(|message| fail(&message));
+
+    let sdl_context: sdl2::Sdl = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+
+    let title = format!("chip-8-rust - {}", options.rom);
+    let window = video_subsystem.window(&title, (WIDTH * options.scale) as u32, (HEIGHT * options.scale) as u32)
+        .position_centered()
+        .build()
+        .unwrap();
+
+    let mut beeper: Box<dyn Beeper> = if options.mute {
+        Box::new(NullBeeper)
+    } else {
+        match SdlBeeper::new(&sdl_context, options.pitch, options.volume) {
+            Ok(beeper) => Box::new(beeper),
+            Err(message) => {
+                eprintln!("warning: audio disabled, {}", message);
+                Box::new(NullBeeper)
+            }
+        }
+    };
+
+    let mut canvas = window.into_canvas().build().unwrap();
+    let textures = canvas.texture_creator();
+    let mut display = SdlDisplay::new(&mut canvas, &textures);
+    let mut screen: Screen = Screen::new();
+
+    screen.scale = options.scale;
+    display.setup();
+
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    configure(&mut processor, rom, options, quirks);
+
+    let mut debugger: Option<Debugger> = options.debug.then(|| Debugger::new(true));
+    if debugger.is_some() {
+        println!("{}", debugger::HELP);
+    }
+
+    let mut crashed = false;
+    let mut slot: u8 = 0;
+    let mut scheduler = Scheduler::new(options.speed, Instant::now());
+    let mut event_pump = sdl_context.event_pump().unwrap();
+
+    'running: loop {
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    break 'running
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F5), .. } => {
+                    save_slot(&processor, &options.rom, slot);
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F9), .. } => {
+                    load_slot(&mut processor, &options.rom, slot);
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F6), .. } => {
+                    slot = (slot + SAVE_SLOTS - 1) % SAVE_SLOTS;
+                    println!("save slot {}", slot);
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F7), .. } => {
+                    slot = (slot + 1) % SAVE_SLOTS;
+                    println!("save slot {}", slot);
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F1), .. } => {
+                    if let Some(debugger) = &mut debugger {
+                        debugger.pause(&processor, "paused from keyboard");
+                    }
+                },
+
+                Event::KeyDown { keycode: Some(Keycode::F12), .. } => {
+                    save_screenshot(processor.screen, options.scale);
+                },
+
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    if let Some(key) = keymap.get(keycode) {
+                        processor.keypad.press(key);
+                    }
+                },
+
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    if let Some(key) = keymap.get(keycode) {
+                        processor.keypad.release(key);
+                    }
+                },
+
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => {
+                    processor.keypad.release_all();
+                },
+
+                _ => {}
+            }
+        }
+
+        'frames: for _ in 0..scheduler.frames_due(Instant::now()) {
+            if crashed {
+                break;
+            }
+
+            for _ in 0..scheduler.next_frame_instructions() {
+                if debugger.as_mut().is_some_and(|debugger| debugger.should_break(&mut processor)) {
+                    break 'frames;
+                }
+
+                if let Err(err) = processor.cycle_cpu() {
+                    // With the debugger the state can still be inspected, otherwise
+                    // the window stays open on the last frame with the error.
+                    match &mut debugger {
+                        Some(debugger) => debugger.pause(&processor, &err.to_string()),
+                        None => {
+                            crashed = true;
+                            report_crash(&processor, &mut display, &options.rom, &err);
+                        },
+                    }
+
+                    break 'frames;
+                }
+            }
+
+            processor.tick();
+        }
+
+        report_logged_opcodes(&mut processor);
+
+        // The timers stop with the program, so the beep has to be stopped here.
+        if crashed {
+            beeper.pause();
+        } else {
+            beeper.set_pattern(processor.audio_pattern.as_ref(), processor.pitch);
+            beeper.update(processor.sound_timer);
+        }
+
+        processor.screen.present(&mut display).unwrap();
+
+        if let Some(debugger) = debugger.as_mut().filter(|debugger| debugger.paused) {
+            beeper.pause();
+
+            if debugger.prompt(&mut processor) == Action::Quit {
+                break 'running;
+            }
+
+            continue;
+        }
+
+        ::std::thread::sleep(scheduler.time_until_next_frame(Instant::now()));
+    }
+
+}
+
+/// Shows why the program stopped, in the terminal and the window title.
+fn report_crash(processor: &Processor, display: &mut dyn Display, rom: &str, err: &EmuError) {
+    eprintln!("error: {}", err);
+    eprint!("{}", processor.register_dump());
+
+    display.set_title(&format!("chip-8-rust - {} - stopped: {}", rom, err));
+}
+
+/// Saves the current framebuffer next to where the emulator was started.
+fn save_screenshot(screen: &Screen, scale: usize) {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = format!("screenshot-{}.png", seconds);
+
+    match screen.save_image(&path, scale) {
+        Ok(()) => println!("saved screenshot to {}", path),
+        Err(err) => eprintln!("error: could not save screenshot '{}': {}", path, err),
+    }
+}
+
+/// Path of the save state file for the slot, stored next to the ROM.
+fn slot_path(rom: &str, slot: u8) -> String {
+    format!("{}.state{}", rom, slot)
+}
+
+/// Writes the current machine state to the slot.
+fn save_slot(processor: &Processor, rom: &str, slot: u8) {
+    let path = slot_path(rom, slot);
+
+    match fs::write(&path, processor.save_state().to_bytes()) {
+        Ok(()) => println!("saved state to slot {}", slot),
+        Err(err) => eprintln!("error: could not save state '{}': {}", path, err),
+    }
+}
+
+/// Restores the machine state from the slot.
+fn load_slot(processor: &mut Processor, rom: &str, slot: u8) {
+    let path = slot_path(rom, slot);
+
+    let result = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|data| SaveState::from_bytes(&data).map_err(|err| err.to_string()))
+        .and_then(|state| processor.load_state(&state).map_err(|err| err.to_string()));
+
+    match result {
+        Ok(()) => println!("loaded state from slot {}", slot),
+        Err(err) => eprintln!("error: could not load state '{}': {}", path, err),
+    }
+}
@@ -0,0 +1,100 @@
+//! Regression tests that run ROMs headless through the library's public API
+//! and compare the final framebuffer against golden images stored as ASCII
+//! art in roms/golden.
+//!
+//! Run with UPDATE_GOLDEN=1 to write the current output as the new golden images.
+
+use std::env;
+use std::fs;
+
+use chip_8_rust::headless::{frame_limit, run_frames};
+use chip_8_rust::processor::processor::Processor;
+use chip_8_rust::scheduler::DEFAULT_SPEED;
+use chip_8_rust::screen::Screen;
+
+const ROM_DIR: &str = "roms";
+const GOLDEN_DIR: &str = "roms/golden";
+
+/// Runs the ROM for the given number of instructions and returns the
+/// framebuffer rendered as ASCII art.
+fn run_rom(rom: &str, cycles: u64) -> String {
+    let path = format!("{}/{}", ROM_DIR, rom);
+    let program = fs::read(&path).unwrap_or_else(|err| panic!("could not read '{}': {}", path, err));
+
+    let mut screen: Screen = Screen::new();
+    let mut processor: Processor = Processor::new(&mut screen);
+
+    processor.load(program).unwrap();
+    run_frames(&mut processor, DEFAULT_SPEED, frame_limit(None, Some(cycles)), Some(cycles))
+        .unwrap_or_else(|err| panic!("'{}' failed: {}", path, err));
+
+    processor.screen.to_ascii()
+}
+
+/// Lists the rows that differ between the two framebuffers, with a marker
+/// line pointing at the differing columns.
+fn diff(expected: &str, actual: &str) -> String {
+    let mut report = String::new();
+    let expected_rows: Vec<&str> = expected.lines().collect();
+    let actual_rows: Vec<&str> = actual.lines().collect();
+
+    for y in 0..expected_rows.len().max(actual_rows.len()) {
+        let want = expected_rows.get(y).copied().unwrap_or("");
+        let got = actual_rows.get(y).copied().unwrap_or("");
+
+        if want == got {
+            continue;
+        }
+
+        let markers: String = (0..want.len().max(got.len()))
+            .map(|x| if want.as_bytes().get(x) == got.as_bytes().get(x) { ' ' } else { '^' })
+            .collect();
+
+        report.push_str(&format!("row {:2} expected {}\n", y, want));
+        report.push_str(&format!("       actual   {}\n", got));
+        report.push_str(&format!("                {}\n", markers));
+    }
+
+    report
+}
+
+/// Compares the output of the ROM with its golden image, panicking with a
+/// readable diff when they do not match.
+fn check_golden(rom: &str, cycles: u64) {
+    let actual = run_rom(rom, cycles);
+    let golden = format!("{}/{}.txt", GOLDEN_DIR, rom);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &actual).unwrap_or_else(|err| panic!("could not write '{}': {}", golden, err));
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|err| panic!("could not read '{}': {}, run with UPDATE_GOLDEN=1 to create it", golden, err));
+
+    if expected != actual {
+        panic!("{} does not match {} after {} cycles\n\n{}", rom, golden, cycles, diff(&expected, &actual));
+    }
+}
+
+#[test]
+fn test_opcode_rom() {
+    check_golden("test_opcode.ch8", 1000);
+}
+
+#[test]
+fn test_ibm_rom() {
+    check_golden("ibm.ch8", 100);
+}
+
+#[test]
+fn test_recursion_rom() {
+    check_golden("recursion.ch8", 500);
+}
+
+#[test]
+fn test_diff() {
+    let report = diff("..#\n...\n", "..#\n.#.\n");
+
+    assert_eq!(report, "row  1 expected ...\n       actual   .#.\n                 ^ \n");
+}